@@ -260,4 +260,23 @@ where
 			Refund(amount) => limit.saturating_add(*amount),
 		}
 	}
+
+	/// Splits the deposit (this type) at `limit`.
+	///
+	/// Returns the deposit clamped to `limit` and the amount by which it exceeds `limit`.
+	///
+	/// # Note
+	///
+	/// A refund always fits into `limit`. Hence it is returned unchanged and never overshoots.
+	pub fn split_at_limit(&self, limit: &Balance) -> (Self, Balance)
+	where
+		Balance: Zero,
+	{
+		use StorageDeposit::*;
+		match self {
+			Charge(amount) if amount > limit => (Charge(*limit), amount.saturating_sub(*limit)),
+			Charge(amount) => (Charge(*amount), Zero::zero()),
+			Refund(amount) => (Refund(*amount), Zero::zero()),
+		}
+	}
 }
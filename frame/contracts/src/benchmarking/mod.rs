@@ -31,7 +31,7 @@ use self::{
 use crate::{
 	exec::{AccountIdOf, Key},
 	migration::{
-		codegen::LATEST_MIGRATION_VERSION, v09, v10, v11, v12, v13, v14, v15, MigrationStep,
+		codegen::LATEST_MIGRATION_VERSION, v09, v10, v11, v12, v13, v14, v15, v16, MigrationStep,
 	},
 	wasm::CallFlags,
 	Pallet as Contracts, *,
@@ -311,6 +311,19 @@ benchmarks! {
 		m.step();
 	}
 
+	// This benchmarks the v16 migration step (track the large value surcharge separately).
+	#[pov_mode = Measured]
+	v16_migration_step {
+		let contract = <Contract<T>>::with_caller(
+			whitelisted_caller(), WasmModule::dummy(), vec![],
+		)?;
+
+		v16::store_old_contract_info::<T>(contract.account_id.clone(), contract.info()?);
+		let mut m = v16::Migration::<T>::default();
+	}: {
+		m.step();
+	}
+
 	// This benchmarks the weight of executing Migration::migrate to execute a noop migration.
 	#[pov_mode = Measured]
 	migration_noop {
@@ -20,8 +20,8 @@ use crate::{
 	gas::GasMeter,
 	storage::{self, meter::Diff, WriteOutcome},
 	BalanceOf, CodeHash, CodeInfo, CodeInfoOf, Config, ContractInfo, ContractInfoOf,
-	DebugBufferVec, Determinism, Error, Event, Nonce, Origin, Pallet as Contracts, Schedule,
-	WasmBlob, LOG_TARGET,
+	DebugBufferVec, Determinism, Error, Event, HoldReason, Nonce, Origin, Pallet as Contracts,
+	Schedule, WasmBlob, LOG_TARGET,
 };
 use frame_support::{
 	crypto::ecdsa::ECDSAExt,
@@ -276,6 +276,25 @@ pub trait Ext: sealing::Sealed {
 	/// Charges `diff` from the meter.
 	fn charge_storage(&mut self, diff: &Diff);
 
+	/// Charges `diff` for transient storage from the meter.
+	///
+	/// It counts against the storage deposit limit but is never charged as transient storage is
+	/// discarded at the end of the transaction.
+	fn charge_transient_storage(&mut self, diff: &Diff);
+
+	/// Returns the storage deposit the current frame is still allowed to consume.
+	///
+	/// The deposit charged by the current frame itself is only taken into account once the
+	/// frame returns.
+	fn storage_deposit_available(&self) -> BalanceOf<Self::T>;
+
+	/// Returns the storage deposit limit of the current frame.
+	fn storage_deposit_limit(&self) -> BalanceOf<Self::T>;
+
+	/// Returns the storage deposit of all the calls that were made by the current frame and
+	/// have already returned.
+	fn storage_deposit_of_returned_calls(&self) -> StorageDeposit<BalanceOf<Self::T>>;
+
 	/// Append a string to the debug buffer.
 	///
 	/// It is added as-is without any additional new line.
@@ -781,6 +800,8 @@ where
 		deposit_limit: BalanceOf<T>,
 		determinism: Determinism,
 	) -> Result<(Frame<T>, E, Option<u64>), ExecError> {
+		// The first frame always takes whatever is left in the root meter.
+		debug_assert!(!storage::meter::GenericMeter::<T, S>::is_root() || deposit_limit.is_zero());
 		let (account_id, contract_info, executable, delegate_caller, entry_point, nonce) =
 			match frame_args {
 				FrameArgs::Call { dest, cached_info, delegated_call } => {
@@ -827,6 +848,12 @@ where
 			return Err(Error::<T>::Indeterministic.into())
 		}
 
+		// The storage deposit charged by a delegate call is held under its own reason.
+		let reason = if delegate_caller.is_some() {
+			HoldReason::DelegateCallDepositReserve
+		} else {
+			HoldReason::StorageDepositReserve
+		};
 		let frame = Frame {
 			delegate_caller,
 			value_transferred,
@@ -834,7 +861,7 @@ where
 			account_id,
 			entry_point,
 			nested_gas: gas_meter.nested(gas_limit)?,
-			nested_storage: storage_meter.nested(deposit_limit),
+			nested_storage: storage_meter.nested_with_reason(deposit_limit, reason)?,
 			allows_reentry: true,
 		};
 
@@ -1034,6 +1061,7 @@ where
 
 			// Only gas counter changes are persisted in case of a failure.
 			if !persist {
+				prev.nested_storage.absorb_reverted(frame.nested_storage);
 				return
 			}
 
@@ -1076,6 +1104,8 @@ where
 			}
 			self.gas_meter.absorb_nested(mem::take(&mut self.first_frame.nested_gas));
 			if !persist {
+				self.storage_meter
+					.absorb_reverted(mem::take(&mut self.first_frame.nested_storage));
 				return
 			}
 			let mut contract = self.first_frame.contract_info.as_contract();
@@ -1421,6 +1451,22 @@ where
 		self.top_frame_mut().nested_storage.charge(diff)
 	}
 
+	fn charge_transient_storage(&mut self, diff: &Diff) {
+		self.top_frame_mut().nested_storage.charge_transient(diff)
+	}
+
+	fn storage_deposit_available(&self) -> BalanceOf<T> {
+		self.top_frame().nested_storage.available()
+	}
+
+	fn storage_deposit_limit(&self) -> BalanceOf<T> {
+		self.top_frame().nested_storage.limit()
+	}
+
+	fn storage_deposit_of_returned_calls(&self) -> StorageDeposit<BalanceOf<T>> {
+		self.top_frame().nested_storage.total_deposit().clone()
+	}
+
 	fn append_debug_buffer(&mut self, msg: &str) -> bool {
 		if let Some(buffer) = &mut self.debug_message {
 			buffer
@@ -1768,8 +1814,7 @@ mod tests {
 			let schedule = <Test as Config>::Schedule::get();
 			place_contract(&BOB, exec_ch);
 			let mut storage_meter =
-				storage::meter::Meter::new(&Origin::from_account_id(ALICE), Some(0), value)
-					.unwrap();
+				storage::meter::Meter::new(&Origin::from_account_id(ALICE), None, value).unwrap();
 
 			assert_matches!(
 				MockStack::run_call(
@@ -1826,7 +1871,7 @@ mod tests {
 			let balance = get_balance(&dest);
 			let contract_origin = Origin::from_account_id(origin.clone());
 			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), value).unwrap();
+				storage::meter::Meter::new(&contract_origin, None, value).unwrap();
 
 			let _ = MockStack::run_call(
 				contract_origin.clone(),
@@ -1869,8 +1914,7 @@ mod tests {
 			set_balance(&origin, 100);
 			let balance = get_balance(&dest);
 			let contract_origin = Origin::from_account_id(origin.clone());
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 55).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 55).unwrap();
 
 			let _ = MockStack::run_call(
 				contract_origin.clone(),
@@ -1907,8 +1951,7 @@ mod tests {
 			set_balance(&origin, 100);
 			let balance = get_balance(&dest);
 			let contract_origin = Origin::from_account_id(origin.clone());
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 55).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 55).unwrap();
 
 			let output = MockStack::run_call(
 				contract_origin.clone(),
@@ -1960,8 +2003,7 @@ mod tests {
 		ExtBuilder::default().build().execute_with(|| {
 			let schedule = <Test as Config>::Schedule::get();
 			let contract_origin = Origin::from_account_id(origin);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			place_contract(&BOB, return_ch);
 
 			let result = MockStack::run_call(
@@ -1996,8 +2038,7 @@ mod tests {
 			let schedule = <Test as Config>::Schedule::get();
 			place_contract(&BOB, return_ch);
 			let contract_origin = Origin::from_account_id(origin);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2029,8 +2070,7 @@ mod tests {
 			let schedule = <Test as Config>::Schedule::get();
 			place_contract(&BOB, input_data_ch);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2117,7 +2157,7 @@ mod tests {
 			place_contract(&BOB, recurse_ch);
 			let contract_origin = Origin::from_account_id(ALICE);
 			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), value).unwrap();
+				storage::meter::Meter::new(&contract_origin, None, value).unwrap();
 
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2172,8 +2212,7 @@ mod tests {
 			place_contract(&dest, bob_ch);
 			place_contract(&CHARLIE, charlie_ch);
 			let contract_origin = Origin::from_account_id(origin.clone());
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			let result = MockStack::run_call(
 				contract_origin.clone(),
@@ -2209,8 +2248,7 @@ mod tests {
 			place_contract(&BOB, bob_ch);
 
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			let result = MockStack::run_call(
 				contract_origin,
 				BOB,
@@ -2240,8 +2278,7 @@ mod tests {
 			let schedule = <Test as Config>::Schedule::get();
 			place_contract(&BOB, code_bob);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			// ALICE (not contract) -> BOB (contract)
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2270,8 +2307,7 @@ mod tests {
 			let schedule = <Test as Config>::Schedule::get();
 			place_contract(&BOB, bob_ch);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			// ALICE (not contract) -> BOB (contract)
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2309,8 +2345,7 @@ mod tests {
 			place_contract(&BOB, code_bob);
 			place_contract(&CHARLIE, code_charlie);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			// ALICE -> BOB (caller is origin) -> CHARLIE (caller is not origin)
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2339,8 +2374,7 @@ mod tests {
 			let schedule = <Test as Config>::Schedule::get();
 			place_contract(&BOB, code_bob);
 			let contract_origin = Origin::Root;
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			// root -> BOB (caller is root)
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2369,8 +2403,7 @@ mod tests {
 			let schedule = <Test as Config>::Schedule::get();
 			place_contract(&BOB, code_bob);
 			let contract_origin = Origin::Root;
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			// root -> BOB (caller is root)
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2408,8 +2441,7 @@ mod tests {
 			place_contract(&BOB, code_bob);
 			place_contract(&CHARLIE, code_charlie);
 			let contract_origin = Origin::Root;
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			// root -> BOB (caller is root) -> CHARLIE (caller is not root)
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2450,8 +2482,7 @@ mod tests {
 			place_contract(&BOB, bob_ch);
 			place_contract(&CHARLIE, charlie_ch);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2478,8 +2509,7 @@ mod tests {
 			let mut gas_meter = GasMeter::<Test>::new(GAS_LIMIT);
 			let executable = MockExecutable::from_storage(dummy_ch, &mut gas_meter).unwrap();
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			assert_matches!(
 				MockStack::run_instantiate(
@@ -2825,8 +2855,7 @@ mod tests {
 			place_contract(&BOB, code_bob);
 			place_contract(&CHARLIE, code_charlie);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			let result = MockStack::run_call(
 				contract_origin,
@@ -2899,8 +2928,7 @@ mod tests {
 			set_balance(&ALICE, min_balance * 10);
 			place_contract(&BOB, code_hash);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			MockStack::run_call(
 				contract_origin,
 				BOB,
@@ -2935,8 +2963,7 @@ mod tests {
 			set_balance(&ALICE, min_balance * 10);
 			place_contract(&BOB, code_hash);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			let result = MockStack::run_call(
 				contract_origin,
 				BOB,
@@ -2974,8 +3001,7 @@ mod tests {
 			set_balance(&ALICE, min_balance * 10);
 			place_contract(&BOB, code_hash);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			MockStack::run_call(
 				contract_origin,
 				BOB,
@@ -3007,8 +3033,7 @@ mod tests {
 			place_contract(&BOB, code_bob);
 			place_contract(&CHARLIE, code_charlie);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			// Calling another contract should succeed
 			assert_ok!(MockStack::run_call(
@@ -3063,8 +3088,7 @@ mod tests {
 			place_contract(&BOB, code_bob);
 			place_contract(&CHARLIE, code_charlie);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 
 			// BOB -> CHARLIE -> BOB fails as BOB denies reentry.
 			assert_err!(
@@ -3102,8 +3126,7 @@ mod tests {
 			set_balance(&ALICE, min_balance * 10);
 			place_contract(&BOB, code_hash);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			System::reset_events();
 			MockStack::run_call(
 				contract_origin,
@@ -3189,8 +3212,7 @@ mod tests {
 			set_balance(&ALICE, min_balance * 10);
 			place_contract(&BOB, code_hash);
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			System::reset_events();
 			MockStack::run_call(
 				contract_origin,
@@ -3356,6 +3378,112 @@ mod tests {
 			});
 	}
 
+	#[test]
+	fn storage_deposit_of_returned_calls_is_exposed() {
+		let callee_ch = MockLoader::insert(Call, |ctx, _| {
+			ctx.ext.charge_storage(&Diff::default().with_bytes_added(10));
+			assert_eq!(ctx.ext.storage_deposit_of_returned_calls(), StorageDeposit::Charge(0));
+			exec_success()
+		});
+		let caller_ch = MockLoader::insert(Call, |ctx, _| {
+			assert_eq!(ctx.ext.storage_deposit_of_returned_calls(), StorageDeposit::Charge(0));
+			ctx.ext.call(Weight::zero(), 0, CHARLIE, 0, vec![], true).unwrap();
+			assert_eq!(ctx.ext.storage_deposit_of_returned_calls(), StorageDeposit::Charge(10));
+			assert_eq!(ctx.ext.storage_deposit_available(), 90);
+			exec_success()
+		});
+
+		ExtBuilder::default().build().execute_with(|| {
+			let schedule = <Test as Config>::Schedule::get();
+			let mut gas_meter = GasMeter::<Test>::new(GAS_LIMIT);
+			set_balance(&ALICE, 1000);
+			place_contract(&BOB, caller_ch);
+			place_contract(&CHARLIE, callee_ch);
+			let contract_origin = Origin::from_account_id(ALICE);
+			let mut storage_meter =
+				storage::meter::Meter::new(&contract_origin, Some(100), 0).unwrap();
+			assert_ok!(MockStack::run_call(
+				contract_origin,
+				BOB,
+				&mut gas_meter,
+				&mut storage_meter,
+				&schedule,
+				0,
+				vec![],
+				None,
+				Determinism::Enforced
+			));
+		});
+	}
+
+	#[test]
+	fn transient_storage_counts_against_the_limit() {
+		let code_hash = MockLoader::insert(Call, |ctx, _| {
+			ctx.ext.charge_transient_storage(&Diff::default().with_bytes_added(10));
+			assert_eq!(ctx.ext.storage_deposit_available(), 90);
+			ctx.ext.charge_transient_storage(&Diff::default().with_bytes_removed(10));
+			assert_eq!(ctx.ext.storage_deposit_available(), 100);
+			exec_success()
+		});
+
+		ExtBuilder::default().build().execute_with(|| {
+			let schedule = <Test as Config>::Schedule::get();
+			let mut gas_meter = GasMeter::<Test>::new(GAS_LIMIT);
+			set_balance(&ALICE, 1000);
+			place_contract(&BOB, code_hash);
+			let contract_origin = Origin::from_account_id(ALICE);
+			let mut storage_meter =
+				storage::meter::Meter::new(&contract_origin, Some(100), 0).unwrap();
+			assert_ok!(MockStack::run_call(
+				contract_origin,
+				BOB,
+				&mut gas_meter,
+				&mut storage_meter,
+				&schedule,
+				0,
+				vec![],
+				None,
+				Determinism::Enforced
+			));
+		});
+	}
+
+	#[test]
+	fn storage_deposit_limit_is_exposed() {
+		let callee_ch = MockLoader::insert(Call, |ctx, _| {
+			assert_eq!(ctx.ext.storage_deposit_limit(), 30);
+			assert_eq!(ctx.ext.storage_deposit_available(), 30);
+			exec_success()
+		});
+		let caller_ch = MockLoader::insert(Call, |ctx, _| {
+			assert_eq!(ctx.ext.storage_deposit_limit(), 100);
+			assert_eq!(ctx.ext.storage_deposit_available(), 100);
+			ctx.ext.call(Weight::zero(), 30, CHARLIE, 0, vec![], true)
+		});
+
+		ExtBuilder::default().build().execute_with(|| {
+			let schedule = <Test as Config>::Schedule::get();
+			let mut gas_meter = GasMeter::<Test>::new(GAS_LIMIT);
+			set_balance(&ALICE, 1000);
+			place_contract(&BOB, caller_ch);
+			place_contract(&CHARLIE, callee_ch);
+			let contract_origin = Origin::from_account_id(ALICE);
+			let mut storage_meter =
+				storage::meter::Meter::new(&contract_origin, Some(100), 0).unwrap();
+			assert_ok!(MockStack::run_call(
+				contract_origin,
+				BOB,
+				&mut gas_meter,
+				&mut storage_meter,
+				&schedule,
+				0,
+				vec![],
+				None,
+				Determinism::Enforced
+			));
+		});
+	}
+
 	#[test]
 	fn set_storage_works() {
 		let code_hash = MockLoader::insert(Call, |ctx, _| {
@@ -3766,8 +3894,7 @@ mod tests {
 			place_contract(&BOB, bob_ch);
 
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			let result = MockStack::run_call(
 				contract_origin,
 				BOB,
@@ -3865,8 +3992,7 @@ mod tests {
 			place_contract(&BOB, code_hash);
 
 			let contract_origin = Origin::from_account_id(ALICE);
-			let mut storage_meter =
-				storage::meter::Meter::new(&contract_origin, Some(0), 0).unwrap();
+			let mut storage_meter = storage::meter::Meter::new(&contract_origin, None, 0).unwrap();
 			let result = MockStack::run_call(
 				contract_origin,
 				BOB,
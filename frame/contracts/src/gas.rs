@@ -185,6 +185,15 @@ impl<T: Config> GasMeter<T> {
 		self.gas_left = self.gas_left.saturating_add(adjustment).min(self.gas_limit);
 	}
 
+	/// Credit `amount` of gas back to the meter.
+	///
+	/// This is used to reward actions after they happened, e.g. freeing storage. It never
+	/// reduces the amount of gas that is required to run the same call.
+	pub fn credit(&mut self, amount: Weight) {
+		self.gas_left_lowest = self.gas_left_lowest();
+		self.gas_left = self.gas_left.saturating_add(amount).min(self.gas_limit);
+	}
+
 	/// This method is used for gas syncs with the engine.
 	///
 	/// Updates internal `engine_comsumed` tracker of engine fuel consumption.
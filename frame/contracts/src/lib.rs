@@ -118,7 +118,7 @@ use frame_support::{
 	ensure,
 	error::BadOrigin,
 	traits::{
-		fungible::{Inspect, Mutate, MutateHold},
+		fungible::{Inspect, InspectHold, Mutate, MutateHold},
 		ConstU32, Contains, Get, Randomness, Time,
 	},
 	weights::Weight,
@@ -137,8 +137,8 @@ use pallet_contracts_primitives::{
 use scale_info::TypeInfo;
 use smallvec::Array;
 use sp_runtime::{
-	traits::{Convert, Hash, Saturating, StaticLookup, Zero},
-	RuntimeDebug,
+	traits::{Bounded, Convert, Hash, Saturating, StaticLookup, Zero},
+	RuntimeDebug, SaturatedConversion,
 };
 use sp_std::{fmt::Debug, prelude::*};
 
@@ -149,6 +149,7 @@ pub use crate::{
 	migration::{MigrateSequence, Migration, NoopMigration},
 	pallet::*,
 	schedule::{HostFnWeights, InstructionWeights, Limits, Schedule},
+	storage::meter::{Limit as StorageDepositLimit, StorageRefundRebate},
 	wasm::Determinism,
 };
 pub use weights::WeightInfo;
@@ -226,7 +227,7 @@ pub mod pallet {
 	use sp_runtime::Perbill;
 
 	/// The current storage version.
-	pub(crate) const STORAGE_VERSION: StorageVersion = StorageVersion::new(15);
+	pub(crate) const STORAGE_VERSION: StorageVersion = StorageVersion::new(16);
 
 	#[pallet::pallet]
 	#[pallet::storage_version(STORAGE_VERSION)]
@@ -327,6 +328,33 @@ pub mod pallet {
 		#[pallet::constant]
 		type DepositPerItem: Get<BalanceOf<Self>>;
 
+		/// A surcharge for storing large values given as `(threshold, deposit_per_byte)`.
+		///
+		/// Every byte of a storage item exceeding `threshold` bytes is charged with
+		/// `deposit_per_byte` in addition to [`Self::DepositPerByte`]. Use `()` to disable it.
+		/// The surcharge is tracked separately from the rest of the storage deposit and only
+		/// refunded when bytes exceeding the threshold are removed.
+		///
+		/// # Note
+		///
+		/// Changing this value for an existing chain might need a storage migration.
+		#[pallet::constant]
+		type LargeValueSurcharge: Get<(u32, BalanceOf<Self>)>;
+
+		/// The weight that is credited back to the caller when a contract frees storage.
+		///
+		/// Use `()` in order to not give any rebate.
+		type StorageRefundRebate: StorageRefundRebate;
+
+		/// The maximum storage deposit that a single contract can accumulate for the storage
+		/// within its child trie.
+		///
+		/// This is independent of the storage deposit limit of any call. A call that would push a
+		/// contract above this ceiling fails with [`Error::ContractDepositLimitExhausted`]. `None`
+		/// means that there is no such ceiling.
+		#[pallet::constant]
+		type MaxDepositPerContract: Get<Option<BalanceOf<Self>>>;
+
 		/// The percentage of the storage deposit that should be held for using a code hash.
 		/// Instantiating a contract, or calling [`chain_extension::Ext::add_delegate_dependency`]
 		/// protects the code from being removed. In order to prevent abuse these actions are
@@ -497,6 +525,14 @@ pub mod pallet {
 				T::MaxDebugBufferLen::get(),
 			)
 		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+			for (contract, info) in ContractInfoOf::<T>::iter() {
+				info.validate_deposit_consistency(&contract)?;
+			}
+			Ok(())
+		}
 	}
 
 	#[pallet::call]
@@ -708,12 +744,14 @@ pub mod pallet {
 				value,
 				data,
 				gas_limit: gas_limit.into(),
-				storage_deposit_limit: storage_deposit_limit.map(Into::into),
+				storage_deposit_limit: storage_deposit_limit
+					.map(|limit| StorageDepositLimit::Absolute(limit.into())),
 				debug_message: None,
 			};
 			let dest = T::Lookup::lookup(dest)?;
 			let mut output =
-				CallInput::<T> { dest, determinism: Determinism::Enforced }.run_guarded(common);
+				CallInput::<T> { dest, determinism: Determinism::Enforced, deposit_payer: None }
+					.run_guarded(common);
 			if let Ok(retval) = &output.result {
 				if retval.did_revert() {
 					output.result = Err(<Error<T>>::ContractReverted.into());
@@ -773,9 +811,8 @@ pub mod pallet {
 				None,
 			)?;
 
-			// Reduces the storage deposit limit by the amount that was reserved for the upload.
 			let storage_deposit_limit =
-				storage_deposit_limit.map(|limit| limit.into().saturating_sub(upload_deposit));
+				Self::limit_after_upload(storage_deposit_limit.map(Into::into), upload_deposit)?;
 
 			let data_len = data.len() as u32;
 			let salt_len = salt.len() as u32;
@@ -784,7 +821,7 @@ pub mod pallet {
 				value,
 				data,
 				gas_limit,
-				storage_deposit_limit,
+				storage_deposit_limit: storage_deposit_limit.map(StorageDepositLimit::Absolute),
 				debug_message: None,
 			};
 
@@ -828,7 +865,8 @@ pub mod pallet {
 				value,
 				data,
 				gas_limit,
-				storage_deposit_limit: storage_deposit_limit.map(Into::into),
+				storage_deposit_limit: storage_deposit_limit
+					.map(|limit| StorageDepositLimit::Absolute(limit.into())),
 				debug_message: None,
 			};
 			let mut output = InstantiateInput::<T> { code: WasmCode::CodeHash(code_hash), salt }
@@ -957,6 +995,17 @@ pub mod pallet {
 			to: T::AccountId,
 			amount: BalanceOf<T>,
 		},
+
+		/// A storage deposit refund could not be paid in full because the contract holds less
+		/// deposit than recorded. This happens for example when the held balance was slashed.
+		StorageDepositReconciled {
+			/// The contract from which the deposit was refunded.
+			contract: T::AccountId,
+			/// The refund according to the recorded deposit of the contract.
+			expected: BalanceOf<T>,
+			/// The refund that was actually paid.
+			refunded: BalanceOf<T>,
+		},
 	}
 
 	#[pallet::error]
@@ -1050,6 +1099,17 @@ pub mod pallet {
 		DelegateDependencyAlreadyExists,
 		/// Can not add a delegate dependency to the code hash of the contract itself.
 		CannotAddSelfAsDelegateDependency,
+		/// Origin doesn't have enough balance to keep the value it sends along with the call,
+		/// let alone pay for any storage deposits.
+		StorageDepositLeftoverNotAvailable,
+		/// An explicit storage deposit limit of zero was supplied. Such a limit can't pay for any
+		/// storage. Supply no limit in order to use the default limit instead.
+		ZeroStorageDepositLimit,
+		/// A contract would hold more storage deposit than [`Config::MaxDepositPerContract`].
+		ContractDepositLimitExhausted,
+		/// A storage deposit was supposed to be held under a [`HoldReason`] that is not meant for
+		/// storage deposits.
+		InvalidStorageDepositReason,
 	}
 
 	/// A reason for the pallet contracts placing a hold on funds.
@@ -1059,6 +1119,8 @@ pub mod pallet {
 		CodeUploadDepositReserve,
 		/// The Pallet has reserved it for storage deposit.
 		StorageDepositReserve,
+		/// The Pallet has reserved it for storage deposit charged by a delegate call.
+		DelegateCallDepositReserve,
 	}
 
 	/// A mapping from a contract's code hash to its code.
@@ -1121,6 +1183,14 @@ pub mod pallet {
 		StorageValue<_, migration::Cursor, OptionQuery>;
 }
 
+impl HoldReason {
+	/// All the reasons a storage meter can hold the storage deposit of a contract under.
+	///
+	/// The storage deposit of a contract can be spread over all of them.
+	pub(crate) const STORAGE_DEPOSIT: [Self; 2] =
+		[Self::StorageDepositReserve, Self::DelegateCallDepositReserve];
+}
+
 /// The type of origins supported by the contracts pallet.
 #[derive(Clone, Encode, Decode, PartialEq, TypeInfo, RuntimeDebugNoBound)]
 pub enum Origin<T: Config> {
@@ -1156,7 +1226,7 @@ struct CommonInput<'a, T: Config> {
 	value: BalanceOf<T>,
 	data: Vec<u8>,
 	gas_limit: Weight,
-	storage_deposit_limit: Option<BalanceOf<T>>,
+	storage_deposit_limit: Option<StorageDepositLimit<BalanceOf<T>>>,
 	debug_message: Option<&'a mut DebugBufferVec<T>>,
 }
 
@@ -1164,6 +1234,8 @@ struct CommonInput<'a, T: Config> {
 struct CallInput<T: Config> {
 	dest: T::AccountId,
 	determinism: Determinism,
+	/// The account paying the storage deposit if it is not the origin.
+	deposit_payer: Option<T::AccountId>,
 }
 
 /// Reference to an existing code hash or a new wasm module.
@@ -1300,18 +1372,27 @@ impl<T: Config> Invokable<T> for CallInput<T> {
 		common: CommonInput<T>,
 		mut gas_meter: GasMeter<T>,
 	) -> InternalOutput<T, Self::Output> {
-		let CallInput { dest, determinism } = self;
+		let CallInput { dest, determinism, deposit_payer } = self;
 		let CommonInput { origin, value, data, debug_message, .. } = common;
-		let mut storage_meter =
-			match StorageMeter::new(&origin, common.storage_deposit_limit, common.value) {
-				Ok(meter) => meter,
-				Err(err) =>
-					return InternalOutput {
-						result: Err(err.into()),
-						gas_meter,
-						storage_deposit: Default::default(),
-					},
-			};
+		let storage_meter = match deposit_payer {
+			Some(payer) => StorageMeter::new_with_payer(
+				&origin,
+				payer,
+				common.storage_deposit_limit,
+				common.value,
+			),
+			None =>
+				StorageMeter::new_with_limit(&origin, common.storage_deposit_limit, common.value),
+		};
+		let mut storage_meter = match storage_meter {
+			Ok(meter) => meter,
+			Err(err) =>
+				return InternalOutput {
+					result: Err(err.into()),
+					gas_meter,
+					storage_deposit: Default::default(),
+				},
+		};
 		let schedule = T::Schedule::get();
 		let result = ExecStack::<T, WasmBlob<T>>::run_call(
 			origin.clone(),
@@ -1324,9 +1405,20 @@ impl<T: Config> Invokable<T> for CallInput<T> {
 			debug_message,
 			determinism,
 		);
+		// Freed storage is only refunded if somebody pays for the storage. This is not the case
+		// for a root origin without a payer, so no rebate is given then.
+		let rebate = if storage_meter.deposit_account(&origin).is_some() {
+			storage_meter.rebate()
+		} else {
+			Weight::zero()
+		};
 
 		match storage_meter.try_into_deposit(&origin) {
-			Ok(storage_deposit) => InternalOutput { gas_meter, storage_deposit, result },
+			Ok(storage_deposit) => {
+				// Only credit the rebate once the freed storage was actually refunded.
+				gas_meter.credit(rebate);
+				InternalOutput { gas_meter, storage_deposit, result }
+			},
 			Err(err) => InternalOutput {
 				gas_meter,
 				storage_deposit: Default::default(),
@@ -1361,8 +1453,11 @@ impl<T: Config> Invokable<T> for InstantiateInput<T> {
 			};
 
 			let contract_origin = Origin::from_account_id(origin.clone());
-			let mut storage_meter =
-				StorageMeter::new(&contract_origin, common.storage_deposit_limit, common.value)?;
+			let mut storage_meter = StorageMeter::new_with_limit(
+				&contract_origin,
+				common.storage_deposit_limit,
+				common.value,
+			)?;
 			let CommonInput { value, data, debug_message, .. } = common;
 			let result = ExecStack::<T, WasmBlob<T>>::run_instantiate(
 				origin.clone(),
@@ -1375,8 +1470,10 @@ impl<T: Config> Invokable<T> for InstantiateInput<T> {
 				&salt,
 				debug_message,
 			);
+			let rebate = storage_meter.rebate();
 
 			storage_deposit = storage_meter.try_into_deposit(&contract_origin)?;
+			gas_meter.credit(rebate);
 			result
 		};
 		InternalOutput { result: try_exec(), gas_meter, storage_deposit }
@@ -1428,6 +1525,93 @@ impl<T: Config> Pallet<T> {
 		debug: DebugInfo,
 		collect_events: CollectEvents,
 		determinism: Determinism,
+	) -> ContractExecResult<BalanceOf<T>, EventRecordOf<T>> {
+		Self::bare_call_with_limit(
+			origin,
+			dest,
+			value,
+			gas_limit,
+			storage_deposit_limit.map(StorageDepositLimit::Absolute),
+			data,
+			debug,
+			collect_events,
+			determinism,
+		)
+	}
+
+	/// Same as [`Self::bare_call`] but the `storage_deposit_limit` can also be a percentage of
+	/// the balance that `origin` can spend.
+	///
+	/// This is useful for wallets that don't know the exact storage deposit a call will require.
+	pub fn bare_call_with_limit(
+		origin: T::AccountId,
+		dest: T::AccountId,
+		value: BalanceOf<T>,
+		gas_limit: Weight,
+		storage_deposit_limit: Option<StorageDepositLimit<BalanceOf<T>>>,
+		data: Vec<u8>,
+		debug: DebugInfo,
+		collect_events: CollectEvents,
+		determinism: Determinism,
+	) -> ContractExecResult<BalanceOf<T>, EventRecordOf<T>> {
+		Self::bare_call_paid_by(
+			origin,
+			None,
+			dest,
+			value,
+			gas_limit,
+			storage_deposit_limit,
+			data,
+			debug,
+			collect_events,
+			determinism,
+		)
+	}
+
+	/// Same as [`Self::bare_call_with_limit`] but the storage deposit is paid by `payer` instead
+	/// of `origin`.
+	///
+	/// The `storage_deposit_limit` is checked against the balance of `payer` and all refunds go
+	/// back to it. The balance of `payer` is used without its consent. This is meant for pallets
+	/// that sponsor the storage deposit of their users and must only be exposed to `origin`s
+	/// that `payer` agreed to pay for.
+	pub fn bare_call_sponsored(
+		origin: T::AccountId,
+		payer: T::AccountId,
+		dest: T::AccountId,
+		value: BalanceOf<T>,
+		gas_limit: Weight,
+		storage_deposit_limit: Option<StorageDepositLimit<BalanceOf<T>>>,
+		data: Vec<u8>,
+		debug: DebugInfo,
+		collect_events: CollectEvents,
+		determinism: Determinism,
+	) -> ContractExecResult<BalanceOf<T>, EventRecordOf<T>> {
+		Self::bare_call_paid_by(
+			origin,
+			Some(payer),
+			dest,
+			value,
+			gas_limit,
+			storage_deposit_limit,
+			data,
+			debug,
+			collect_events,
+			determinism,
+		)
+	}
+
+	fn bare_call_paid_by(
+		origin: T::AccountId,
+		deposit_payer: Option<T::AccountId>,
+		dest: T::AccountId,
+		value: BalanceOf<T>,
+		gas_limit: Weight,
+		storage_deposit_limit: Option<StorageDepositLimit<BalanceOf<T>>>,
+		data: Vec<u8>,
+		debug: DebugInfo,
+		collect_events: CollectEvents,
+		determinism: Determinism,
 	) -> ContractExecResult<BalanceOf<T>, EventRecordOf<T>> {
 		ensure_no_migration_in_progress!();
 
@@ -1445,7 +1629,7 @@ impl<T: Config> Pallet<T> {
 			storage_deposit_limit,
 			debug_message: debug_message.as_mut(),
 		};
-		let output = CallInput::<T> { dest, determinism }.run_guarded(common);
+		let output = CallInput::<T> { dest, determinism, deposit_payer }.run_guarded(common);
 		let events = if matches!(collect_events, CollectEvents::UnsafeCollect) {
 			Some(System::<T>::read_events_no_consensus().map(|e| *e).collect())
 		} else {
@@ -1511,7 +1695,12 @@ impl<T: Config> Pallet<T> {
 					storage_deposit_limit.map(Into::into),
 					Determinism::Enforced,
 					debug_message.as_mut(),
-				);
+				)
+				.and_then(|(module, deposit)| {
+					storage_deposit_limit =
+						Self::limit_after_upload(storage_deposit_limit, deposit)?;
+					Ok((module, deposit))
+				});
 
 				let (module, deposit) = match result {
 					Ok(result) => result,
@@ -1526,8 +1715,6 @@ impl<T: Config> Pallet<T> {
 						},
 				};
 
-				storage_deposit_limit =
-					storage_deposit_limit.map(|l| l.saturating_sub(deposit.into()));
 				(WasmCode::Wasm(module), deposit)
 			},
 			Code::Existing(hash) => (WasmCode::CodeHash(hash), Default::default()),
@@ -1538,7 +1725,7 @@ impl<T: Config> Pallet<T> {
 			value,
 			data,
 			gas_limit,
-			storage_deposit_limit,
+			storage_deposit_limit: storage_deposit_limit.map(StorageDepositLimit::Absolute),
 			debug_message: debug_message.as_mut(),
 		};
 
@@ -1596,6 +1783,20 @@ impl<T: Config> Pallet<T> {
 		Ok((module, deposit))
 	}
 
+	/// Reduces the storage deposit `limit` by the `deposit` that was reserved for an upload.
+	///
+	/// Nothing being left is not the same as the caller asking for a zero limit. Hence this
+	/// fails instead of returning a zero limit.
+	fn limit_after_upload(
+		limit: Option<BalanceOf<T>>,
+		deposit: BalanceOf<T>,
+	) -> Result<Option<BalanceOf<T>>, DispatchError> {
+		match limit.map(|limit| limit.saturating_sub(deposit)) {
+			Some(limit) if limit.is_zero() => Err(<Error<T>>::StorageDepositLimitExhausted.into()),
+			limit => Ok(limit),
+		}
+	}
+
 	/// Query storage of a specified contract under a specified key.
 	pub fn get_storage(address: T::AccountId, key: Vec<u8>) -> GetStorageResult {
 		if Migration::<T>::in_progress() {
@@ -1612,6 +1813,44 @@ impl<T: Config> Pallet<T> {
 		Ok(maybe_value)
 	}
 
+	/// Compare the storage deposit recorded for `contract` with the balance actually held.
+	///
+	/// Returns `(recorded, held, drift)` where `drift` is `held - recorded`. A non zero `drift`
+	/// means that the deposit accounting of the contract is out of sync with its held balance.
+	/// This can for example happen when the held balance was slashed. Returns `None` if there is
+	/// no contract at `contract`.
+	pub fn deposit_reconciliation(
+		contract: &T::AccountId,
+	) -> Option<(BalanceOf<T>, BalanceOf<T>, i128)> {
+		let info = ContractInfoOf::<T>::get(contract)?;
+		let recorded = info.recorded_deposit().unwrap_or_else(Bounded::max_value);
+		let held = Self::held_storage_deposit(contract);
+		let abs_drift = |lhs: BalanceOf<T>, rhs: BalanceOf<T>| {
+			i128::try_from(lhs.saturating_sub(rhs).saturated_into::<u128>()).unwrap_or(i128::MAX)
+		};
+		let drift =
+			if held >= recorded { abs_drift(held, recorded) } else { -abs_drift(recorded, held) };
+		Some((recorded, held, drift))
+	}
+
+	/// The balance that is held from `contract` under any of the [`HoldReason::STORAGE_DEPOSIT`]
+	/// reasons.
+	fn held_storage_deposit(contract: &T::AccountId) -> BalanceOf<T> {
+		HoldReason::STORAGE_DEPOSIT.iter().fold(Zero::zero(), |held, reason| {
+			held.saturating_add(T::Currency::balance_on_hold(&(*reason).into(), contract))
+		})
+	}
+
+	/// Returns how much more storage deposit `contract` can accumulate before reaching
+	/// [`Config::MaxDepositPerContract`].
+	///
+	/// Returns `None` if there is no such ceiling or no contract at `contract`.
+	pub fn deposit_headroom(contract: &T::AccountId) -> Option<BalanceOf<T>> {
+		let ceiling = T::MaxDepositPerContract::get()?;
+		let info = ContractInfoOf::<T>::get(contract)?;
+		Some(ceiling.saturating_sub(info.extra_deposit()))
+	}
+
 	/// Determine the address of a contract.
 	///
 	/// This is the address generation function used by contract instantiation. See
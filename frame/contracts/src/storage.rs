@@ -36,7 +36,7 @@ use scale_info::TypeInfo;
 use sp_core::Get;
 use sp_io::KillStorageResult;
 use sp_runtime::{
-	traits::{Hash, Saturating, Zero},
+	traits::{CheckedAdd, Hash, Saturating, Zero},
 	BoundedBTreeMap, DispatchResult, RuntimeDebug,
 };
 use sp_std::{marker::PhantomData, prelude::*};
@@ -60,6 +60,11 @@ pub struct ContractInfo<T: Config> {
 	pub storage_byte_deposit: BalanceOf<T>,
 	/// This records to how much deposit the accumulated `storage_items` amount to.
 	storage_item_deposit: BalanceOf<T>,
+	/// How many of the `storage_bytes` exceed the threshold of [`Config::LargeValueSurcharge`]
+	/// within their storage item.
+	storage_large_value_bytes: u32,
+	/// This records to how much surcharge the accumulated `storage_large_value_bytes` amount to.
+	storage_large_value_deposit: BalanceOf<T>,
 	/// This records how much deposit is put down in order to pay for the contract itself.
 	///
 	/// We need to store this information separately so it is not used when calculating any refunds
@@ -102,6 +107,8 @@ impl<T: Config> ContractInfo<T> {
 			storage_items: 0,
 			storage_byte_deposit: Zero::zero(),
 			storage_item_deposit: Zero::zero(),
+			storage_large_value_bytes: 0,
+			storage_large_value_deposit: Zero::zero(),
 			storage_base_deposit: Zero::zero(),
 			delegate_dependencies: Default::default(),
 		};
@@ -116,7 +123,9 @@ impl<T: Config> ContractInfo<T> {
 
 	/// The deposit paying for the accumulated storage generated within the contract's child trie.
 	pub fn extra_deposit(&self) -> BalanceOf<T> {
-		self.storage_byte_deposit.saturating_add(self.storage_item_deposit)
+		self.storage_byte_deposit
+			.saturating_add(self.storage_item_deposit)
+			.saturating_add(self.storage_large_value_deposit)
 	}
 
 	/// Same as [`Self::extra_deposit`] but including the base deposit.
@@ -131,6 +140,44 @@ impl<T: Config> ContractInfo<T> {
 		self.storage_base_deposit
 	}
 
+	/// The deposit that should be held from the contract according to this contract info.
+	///
+	/// This is [`Self::total_deposit`] plus the deposits for the delegate dependencies which are
+	/// held from the contract, too. Returns `None` if the sum overflows.
+	pub fn recorded_deposit(&self) -> Option<BalanceOf<T>> {
+		// The minimum balance is part of the base deposit but it is not held.
+		let base_deposit = self.storage_base_deposit.saturating_sub(Pallet::<T>::min_balance());
+		self.delegate_dependencies
+			.values()
+			.chain([
+				&self.storage_byte_deposit,
+				&self.storage_item_deposit,
+				&self.storage_large_value_deposit,
+			])
+			.try_fold(base_deposit, |acc, deposit| acc.checked_add(deposit))
+	}
+
+	/// Checks that the deposits recorded in this contract info are consistent with each other
+	/// and with the balance that is held from `contract`.
+	///
+	/// This is meant to be used by `try-runtime` state checks in order to catch deposit
+	/// accounting drift.
+	pub fn validate_deposit_consistency(
+		&self,
+		contract: &AccountIdOf<T>,
+	) -> Result<(), &'static str> {
+		let min_balance = Pallet::<T>::min_balance();
+		if self.storage_base_deposit < min_balance {
+			return Err("Contract base deposit doesn't cover the minimum balance")
+		}
+		let recorded = self.recorded_deposit().ok_or("Contract deposits overflow")?;
+		let held = Pallet::<T>::held_storage_deposit(contract);
+		if recorded > held {
+			return Err("Contract deposits exceed its held balance")
+		}
+		Ok(())
+	}
+
 	/// Reads a storage kv pair of a contract.
 	///
 	/// The read is performed from the `trie_id` only. The `address` is not necessary. If the
@@ -189,6 +236,16 @@ impl<T: Config> ContractInfo<T> {
 				},
 				(None, None) => (),
 			}
+			// Only the part of a value exceeding the threshold is surcharged.
+			let (threshold, _) = T::LargeValueSurcharge::get();
+			let old_excess = old_len.unwrap_or(0).saturating_sub(threshold);
+			let new_excess =
+				new_value.as_ref().map_or(0, |v| (v.len() as u32).saturating_sub(threshold));
+			if new_excess > old_excess {
+				diff.large_value_bytes_added = new_excess - old_excess;
+			} else {
+				diff.large_value_bytes_removed = old_excess - new_excess;
+			}
 			storage_meter.charge(&diff);
 		}
 
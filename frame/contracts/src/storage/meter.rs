@@ -26,18 +26,19 @@ use frame_support::{
 	dispatch::{fmt::Debug, DispatchError},
 	ensure,
 	traits::{
-		fungible::{Mutate, MutateHold},
+		fungible::{InspectHold, Mutate, MutateHold},
 		tokens::{
 			Fortitude, Fortitude::Polite, Precision, Preservation, Restriction, WithdrawConsequence,
 		},
 		Get,
 	},
+	weights::Weight,
 	DefaultNoBound, RuntimeDebugNoBound,
 };
 use sp_api::HashT;
 use sp_runtime::{
-	traits::{Saturating, Zero},
-	FixedPointNumber, FixedU128,
+	traits::{AtLeast32BitUnsigned, CheckedAdd, CheckedMul, Saturating, Zero},
+	FixedPointNumber, FixedU128, Permill, RuntimeDebug,
 };
 use sp_std::{marker::PhantomData, vec, vec::Vec};
 
@@ -55,6 +56,49 @@ pub type NestedMeter<T> = RawMeter<T, ReservingExt, Nested>;
 /// This can be used where we want to be generic over the state (Root vs. Nested).
 pub type GenericMeter<T, S> = RawMeter<T, ReservingExt, S>;
 
+/// Determines the weight that is credited back to the caller when a contract frees storage.
+///
+/// This can be used to incentivize contracts to clean up their storage.
+pub trait StorageRefundRebate {
+	/// Returns the weight rebate for removing `bytes` bytes and `items` items from storage.
+	fn rebate(bytes: u32, items: u32) -> Weight;
+}
+
+/// No rebate is given for freeing storage.
+impl StorageRefundRebate for () {
+	fn rebate(_bytes: u32, _items: u32) -> Weight {
+		Weight::zero()
+	}
+}
+
+/// The storage deposit limit a meter is constructed with.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Limit<Balance> {
+	/// At most this amount of balance can be used for storage deposits.
+	Absolute(Balance),
+	/// At most this fraction of the balance the origin can spend can be used for storage
+	/// deposits. It is resolved once when the meter is created.
+	Percent(Permill),
+}
+
+impl<Balance: AtLeast32BitUnsigned + Copy> Limit<Balance> {
+	/// Returns the absolute limit given that the origin can spend `spendable`.
+	pub fn resolve(self, spendable: Balance) -> Balance {
+		match self {
+			Self::Absolute(limit) => limit,
+			Self::Percent(percent) => percent.mul_floor(spendable),
+		}
+	}
+
+	/// Whether this limit never allows to use any balance.
+	pub fn is_zero(&self) -> bool {
+		match self {
+			Self::Absolute(limit) => limit.is_zero(),
+			Self::Percent(percent) => percent.is_zero(),
+		}
+	}
+}
+
 /// A trait that allows to decouple the metering from the charging of balance.
 ///
 /// This mostly exists for testing so that the charging can be mocked.
@@ -64,7 +108,8 @@ pub trait Ext<T: Config> {
 	/// It is necessary to do this check beforehand so that the charge won't fail later on.
 	///
 	/// `origin`: The origin of the call stack from which is responsible for putting down a deposit.
-	/// `limit`: The limit with which the meter was constructed.
+	/// `limit`: The limit with which the meter was constructed. A [`Limit::Percent`] is resolved
+	/// against the balance that `origin` can spend.
 	/// `min_leftover`: How much `free_balance` in addition to the existential deposit (ed) should
 	/// be left inside the `origin` account.
 	///
@@ -72,7 +117,7 @@ pub trait Ext<T: Config> {
 	/// it returns `Err`.
 	fn check_limit(
 		origin: &T::AccountId,
-		limit: Option<BalanceOf<T>>,
+		limit: Option<Limit<BalanceOf<T>>>,
 		min_leftover: BalanceOf<T>,
 	) -> Result<BalanceOf<T>, DispatchError>;
 	/// This is called to inform the implementer that some balance should be charged due to
@@ -81,12 +126,13 @@ pub trait Ext<T: Config> {
 	/// The balance transfer can either flow from `origin` to `contract` or the other way
 	/// around depending on whether `amount` constitutes a `Charge` or a `Refund`.
 	/// It should be used in combination with `check_limit` to check that no more balance than this
-	/// limit is ever charged.
+	/// limit is ever charged. The deposit is held (or released) under the given `reason`.
 	fn charge(
 		origin: &T::AccountId,
 		contract: &T::AccountId,
 		amount: &DepositOf<T>,
 		state: &ContractState<T>,
+		reason: &HoldReason,
 	) -> Result<(), DispatchError>;
 }
 
@@ -98,7 +144,10 @@ pub enum ReservingExt {}
 /// Used to implement a type state pattern for the meter.
 ///
 /// It is sealed and cannot be implemented outside of this module.
-pub trait State: private::Sealed {}
+pub trait State: private::Sealed {
+	/// Whether this is the [`Root`] state.
+	const IS_ROOT: bool;
+}
 
 /// State parameter that constitutes a meter that is in its root state.
 #[derive(Default, Debug)]
@@ -113,11 +162,16 @@ pub enum Nested {
 	OwnLimit,
 }
 
-impl State for Root {}
-impl State for Nested {}
+impl State for Root {
+	const IS_ROOT: bool = true;
+}
+
+impl State for Nested {
+	const IS_ROOT: bool = false;
+}
 
 /// A type that allows the metering of consumed or freed storage of a single contract call stack.
-#[derive(DefaultNoBound, RuntimeDebugNoBound)]
+#[cfg_attr(not(test), derive(RuntimeDebugNoBound))]
 pub struct RawMeter<T: Config, E, S: State + Default + Debug> {
 	/// The limit of how much balance this meter is allowed to consume.
 	limit: BalanceOf<T>,
@@ -125,18 +179,92 @@ pub struct RawMeter<T: Config, E, S: State + Default + Debug> {
 	total_deposit: DepositOf<T>,
 	/// The amount of storage changes that were recorded in this meter alone.
 	own_contribution: Contribution<T>,
+	/// The deposit that the transient storage created by this meter and all of its already
+	/// absorbed children amounts to.
+	///
+	/// It counts against the limit during execution but is never charged, because transient
+	/// storage is discarded at the end of the transaction.
+	transient_deposit: DepositOf<T>,
 	/// List of charges that should be applied at the end of a contract stack execution.
 	///
 	/// We only have one charge per contract hence the size of this vector is
 	/// limited by the maximum call depth.
 	charges: Vec<Charge<T>>,
+	/// The storage changes of all contracts that were recorded by this meter and its already
+	/// absorbed children. It is used to calculate the [`Self::rebate`].
+	storage_diff: Diff,
 	/// We store the nested state to determine if it has a special limit for sub-call.
 	nested: S,
+	/// The reason under which the deposits recorded by this meter are held.
+	reason: HoldReason,
+	/// The account that pays the storage deposit if it is not the origin of the call stack.
+	///
+	/// Set by [`RawMeter::new_with_payer`] and inherited by all nested meters.
+	payer: Option<T::AccountId>,
 	/// Type parameter only used in impls.
 	_phantom: PhantomData<E>,
 }
 
+/// A meter holds its deposits under [`HoldReason::StorageDepositReserve`] unless it was created
+/// with another reason by [`RawMeter::nested_with_reason`].
+impl<T, E, S> Default for RawMeter<T, E, S>
+where
+	T: Config,
+	S: State + Default + Debug,
+{
+	fn default() -> Self {
+		Self {
+			limit: Default::default(),
+			total_deposit: Default::default(),
+			own_contribution: Default::default(),
+			transient_deposit: Default::default(),
+			charges: Default::default(),
+			storage_diff: Default::default(),
+			nested: Default::default(),
+			reason: HoldReason::StorageDepositReserve,
+			payer: Default::default(),
+			_phantom: PhantomData,
+		}
+	}
+}
+
+/// In tests we also print the computed [`RawMeter::available`] balance in order to make assertion
+/// failures more legible.
+#[cfg(test)]
+impl<T, E, S> Debug for RawMeter<T, E, S>
+where
+	T: Config,
+	E: Ext<T>,
+	S: State + Default + Debug,
+{
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		f.debug_struct("RawMeter")
+			.field("limit", &self.limit)
+			.field("total_deposit", &self.total_deposit)
+			.field("available", &self.available())
+			.field("own_contribution", &self.own_contribution)
+			.field("transient_deposit", &self.transient_deposit)
+			.field("charges", &self.charges)
+			.field("storage_diff", &self.storage_diff)
+			.field("rebate", &self.rebate())
+			.field("nested", &self.nested)
+			.field("reason", &self.reason)
+			.field("payer", &self.payer)
+			.finish()
+	}
+}
+
 /// This type is used to describe a storage change when charging from the meter.
+///
+/// Chain extensions that write to storage on behalf of a contract should build a diff of those
+/// writes and pass it to [`crate::chain_extension::Ext::charge_storage`]. This charges it from
+/// the nested meter of the currently executing contract using the same deposit calculation
+/// that applies to the contract's own storage:
+///
+/// ```ignore
+/// let diff = Diff::new().with_bytes_added(len).with_items_added(1);
+/// env.ext().charge_storage(&diff);
+/// ```
 #[derive(Default, RuntimeDebugNoBound)]
 pub struct Diff {
 	/// How many bytes were added to storage.
@@ -147,9 +275,74 @@ pub struct Diff {
 	pub items_added: u32,
 	/// How many storage items were removed from storage.
 	pub items_removed: u32,
+	/// How many of the added bytes exceed the threshold of [`Config::LargeValueSurcharge`]
+	/// within their storage item.
+	pub large_value_bytes_added: u32,
+	/// How many of the removed bytes exceeded the threshold of [`Config::LargeValueSurcharge`]
+	/// within their storage item.
+	pub large_value_bytes_removed: u32,
 }
 
 impl Diff {
+	/// Create an empty diff that doesn't change any storage.
+	pub fn new() -> Self {
+		Default::default()
+	}
+
+	/// Record that `bytes` were added to storage.
+	pub fn with_bytes_added(mut self, bytes: u32) -> Self {
+		self.bytes_added = self.bytes_added.saturating_add(bytes);
+		self
+	}
+
+	/// Record that `bytes` were removed from storage.
+	pub fn with_bytes_removed(mut self, bytes: u32) -> Self {
+		self.bytes_removed = self.bytes_removed.saturating_add(bytes);
+		self
+	}
+
+	/// Record that `items` were added to storage.
+	pub fn with_items_added(mut self, items: u32) -> Self {
+		self.items_added = self.items_added.saturating_add(items);
+		self
+	}
+
+	/// Record that `items` were removed from storage.
+	pub fn with_items_removed(mut self, items: u32) -> Self {
+		self.items_removed = self.items_removed.saturating_add(items);
+		self
+	}
+
+	/// Record that `bytes` of the added bytes exceed [`Config::LargeValueSurcharge`].
+	///
+	/// Those bytes still need to be recorded with [`Self::with_bytes_added`].
+	pub fn with_large_value_bytes_added(mut self, bytes: u32) -> Self {
+		self.large_value_bytes_added = self.large_value_bytes_added.saturating_add(bytes);
+		self
+	}
+
+	/// Record that `bytes` of the removed bytes exceeded [`Config::LargeValueSurcharge`].
+	///
+	/// Those bytes still need to be recorded with [`Self::with_bytes_removed`].
+	pub fn with_large_value_bytes_removed(mut self, bytes: u32) -> Self {
+		self.large_value_bytes_removed = self.large_value_bytes_removed.saturating_add(bytes);
+		self
+	}
+
+	/// Create a diff that removes all the storage accumulated within the contract of `info`.
+	///
+	/// Applying it with [`Self::update_contract`] refunds the whole storage deposit of the
+	/// contract. This is the same as what [`RawMeter::terminate`] refunds minus the base deposit,
+	/// which can only ever be refunded on termination.
+	pub fn clear_all<T: Config>(info: &ContractInfo<T>) -> Self {
+		Self {
+			bytes_removed: info.storage_bytes,
+			items_removed: info.storage_items,
+			large_value_bytes_removed: info.storage_large_value_bytes,
+			..Default::default()
+		}
+	}
+
 	/// Calculate how much of a charge or refund results from applying the diff and store it
 	/// in the passed `info` if any.
 	///
@@ -161,10 +354,20 @@ impl Diff {
 	pub fn update_contract<T: Config>(&self, info: Option<&mut ContractInfo<T>>) -> DepositOf<T> {
 		let per_byte = T::DepositPerByte::get();
 		let per_item = T::DepositPerItem::get();
+		let (_, per_large_value_byte) = T::LargeValueSurcharge::get();
 		let bytes_added = self.bytes_added.saturating_sub(self.bytes_removed);
 		let items_added = self.items_added.saturating_sub(self.items_removed);
-		let mut bytes_deposit = Deposit::Charge(per_byte.saturating_mul((bytes_added).into()));
-		let mut items_deposit = Deposit::Charge(per_item.saturating_mul((items_added).into()));
+		let large_value_bytes_added =
+			self.large_value_bytes_added.saturating_sub(self.large_value_bytes_removed);
+		let mut bytes_deposit =
+			Deposit::Charge(saturating_scale("bytes deposit", per_byte, bytes_added.into()));
+		let mut items_deposit =
+			Deposit::Charge(saturating_scale("items deposit", per_item, items_added.into()));
+		let mut large_value_deposit = Deposit::Charge(saturating_scale(
+			"large value surcharge",
+			per_large_value_byte,
+			large_value_bytes_added.into(),
+		));
 
 		// Without any contract info we can only calculate diffs which add storage
 		let info = if let Some(info) = info {
@@ -172,7 +375,16 @@ impl Diff {
 		} else {
 			debug_assert_eq!(self.bytes_removed, 0);
 			debug_assert_eq!(self.items_removed, 0);
-			return bytes_deposit.saturating_add(&items_deposit)
+			debug_assert_eq!(self.large_value_bytes_removed, 0);
+			return saturating_accumulate_deposit::<T>(
+				"storage deposit",
+				&saturating_accumulate_deposit::<T>(
+					"storage deposit",
+					&bytes_deposit,
+					&items_deposit,
+				),
+				&large_value_deposit,
+			)
 		};
 
 		// Refunds are calculated pro rata based on the accumulated storage within the contract
@@ -188,26 +400,64 @@ impl Diff {
 			.min(FixedU128::from_u32(1));
 		items_deposit = items_deposit
 			.saturating_add(&Deposit::Refund(ratio.saturating_mul_int(info.storage_item_deposit)));
+		let large_value_bytes_removed =
+			self.large_value_bytes_removed.saturating_sub(self.large_value_bytes_added);
+		let ratio = FixedU128::checked_from_rational(
+			large_value_bytes_removed,
+			info.storage_large_value_bytes,
+		)
+		.unwrap_or_default()
+		.min(FixedU128::from_u32(1));
+		large_value_deposit = large_value_deposit.saturating_add(&Deposit::Refund(
+			ratio.saturating_mul_int(info.storage_large_value_deposit),
+		));
 
 		// We need to update the contract info structure with the new deposits
 		info.storage_bytes =
 			info.storage_bytes.saturating_add(bytes_added).saturating_sub(bytes_removed);
 		info.storage_items =
 			info.storage_items.saturating_add(items_added).saturating_sub(items_removed);
+		info.storage_large_value_bytes = info
+			.storage_large_value_bytes
+			.saturating_add(large_value_bytes_added)
+			.saturating_sub(large_value_bytes_removed);
 		match &bytes_deposit {
 			Deposit::Charge(amount) =>
-				info.storage_byte_deposit = info.storage_byte_deposit.saturating_add(*amount),
+				info.storage_byte_deposit = saturating_accumulate(
+					"storage byte deposit",
+					info.storage_byte_deposit,
+					*amount,
+				),
 			Deposit::Refund(amount) =>
 				info.storage_byte_deposit = info.storage_byte_deposit.saturating_sub(*amount),
 		}
 		match &items_deposit {
 			Deposit::Charge(amount) =>
-				info.storage_item_deposit = info.storage_item_deposit.saturating_add(*amount),
+				info.storage_item_deposit = saturating_accumulate(
+					"storage item deposit",
+					info.storage_item_deposit,
+					*amount,
+				),
 			Deposit::Refund(amount) =>
 				info.storage_item_deposit = info.storage_item_deposit.saturating_sub(*amount),
 		}
+		match &large_value_deposit {
+			Deposit::Charge(amount) =>
+				info.storage_large_value_deposit = saturating_accumulate(
+					"storage large value deposit",
+					info.storage_large_value_deposit,
+					*amount,
+				),
+			Deposit::Refund(amount) =>
+				info.storage_large_value_deposit =
+					info.storage_large_value_deposit.saturating_sub(*amount),
+		}
 
-		bytes_deposit.saturating_add(&items_deposit)
+		saturating_accumulate_deposit::<T>(
+			"storage deposit",
+			&saturating_accumulate_deposit::<T>("storage deposit", &bytes_deposit, &items_deposit),
+			&large_value_deposit,
+		)
 	}
 }
 
@@ -218,6 +468,12 @@ impl Diff {
 			bytes_removed: self.bytes_removed.saturating_add(rhs.bytes_removed),
 			items_added: self.items_added.saturating_add(rhs.items_added),
 			items_removed: self.items_removed.saturating_add(rhs.items_removed),
+			large_value_bytes_added: self
+				.large_value_bytes_added
+				.saturating_add(rhs.large_value_bytes_added),
+			large_value_bytes_removed: self
+				.large_value_bytes_removed
+				.saturating_add(rhs.large_value_bytes_removed),
 		}
 	}
 }
@@ -245,6 +501,7 @@ struct Charge<T: Config> {
 	contract: T::AccountId,
 	amount: DepositOf<T>,
 	state: ContractState<T>,
+	reason: HoldReason,
 }
 
 /// Records the storage changes of a storage meter.
@@ -270,6 +527,17 @@ impl<T: Config> Contribution<T> {
 				deposit.clone(),
 		}
 	}
+
+	/// The storage changes that are not yet accounted for in the `storage_diff` of the meter.
+	///
+	/// The changes of a [`Self::Checked`] contribution were already accounted for when it was
+	/// checked.
+	fn unaccounted_diff(&self) -> Option<&Diff> {
+		match self {
+			Self::Alive(diff) => Some(diff),
+			Self::Terminated { .. } | Self::Checked(_) => None,
+		}
+	}
 }
 
 impl<T: Config> Default for Contribution<T> {
@@ -291,15 +559,50 @@ where
 	/// This is called whenever a new subcall is initiated in order to track the storage
 	/// usage for this sub call separately. This is necessary because we want to exchange balance
 	/// with the current contract we are interacting with.
+	///
+	/// The child inherits the hold reason of its parent.
 	pub fn nested(&self, limit: BalanceOf<T>) -> RawMeter<T, E, Nested> {
+		self.nested_unchecked(limit, self.reason)
+	}
+
+	/// Same as [`Self::nested`] but the deposits recorded by the child are held under `reason`
+	/// instead of the hold reason of its parent.
+	///
+	/// Only the [`HoldReason::STORAGE_DEPOSIT`] reasons are accepted. A refund releases the
+	/// deposit held under its own reason first and then under the other ones.
+	pub fn nested_with_reason(
+		&self,
+		limit: BalanceOf<T>,
+		reason: HoldReason,
+	) -> Result<RawMeter<T, E, Nested>, DispatchError> {
+		ensure!(
+			HoldReason::STORAGE_DEPOSIT.contains(&reason),
+			<Error<T>>::InvalidStorageDepositReason
+		);
+		Ok(self.nested_unchecked(limit, reason))
+	}
+
+	/// Same as [`Self::nested_with_reason`] but without checking `reason`.
+	fn nested_unchecked(&self, limit: BalanceOf<T>, reason: HoldReason) -> RawMeter<T, E, Nested> {
 		debug_assert!(matches!(self.contract_state(), ContractState::Alive));
 		// If a special limit is specified higher than it is available,
 		// we want to enforce the lesser limit to the nested meter, to fail in the sub-call.
 		let limit = self.available().min(limit);
 		if limit.is_zero() {
-			RawMeter { limit: self.available(), ..Default::default() }
+			RawMeter {
+				limit: self.available(),
+				reason,
+				payer: self.payer.clone(),
+				..Default::default()
+			}
 		} else {
-			RawMeter { limit, nested: Nested::OwnLimit, ..Default::default() }
+			RawMeter {
+				limit,
+				nested: Nested::OwnLimit,
+				reason,
+				payer: self.payer.clone(),
+				..Default::default()
+			}
 		}
 	}
 
@@ -325,23 +628,81 @@ where
 		info: Option<&mut ContractInfo<T>>,
 	) {
 		let own_deposit = absorbed.own_contribution.update_contract(info);
-		self.total_deposit = self
-			.total_deposit
-			.saturating_add(&absorbed.total_deposit)
-			.saturating_add(&own_deposit);
+		self.storage_diff = self.storage_diff.saturating_add(&absorbed.storage_diff);
+		if let Some(diff) = absorbed.own_contribution.unaccounted_diff() {
+			self.storage_diff = self.storage_diff.saturating_add(diff);
+		}
+		self.total_deposit = saturating_accumulate_deposit::<T>(
+			"absorbed deposit",
+			&saturating_accumulate_deposit::<T>(
+				"absorbed deposit",
+				&self.total_deposit,
+				&absorbed.total_deposit,
+			),
+			&own_deposit,
+		);
+		self.transient_deposit = self.transient_deposit.saturating_add(&absorbed.transient_deposit);
 		self.charges.extend_from_slice(&absorbed.charges);
 		if !own_deposit.is_zero() {
 			self.charges.push(Charge {
 				contract: contract.clone(),
 				amount: own_deposit,
 				state: absorbed.contract_state(),
+				reason: absorbed.reason,
 			});
 		}
 	}
 
+	/// Discard a child that was spawned to handle a sub call which was reverted.
+	///
+	/// Nothing that was recorded by the child or any of its absorbed children is kept. This is
+	/// the same as just dropping the child. However, it makes the intent explicit and ensures
+	/// that a reverted child is not accidentally absorbed.
+	pub fn absorb_reverted(&mut self, reverted: RawMeter<T, E, Nested>) {
+		log::trace!(
+			target: LOG_TARGET,
+			"Discarding storage deposit of reverted call: {:?}",
+			reverted.total_deposit,
+		);
+	}
+
 	/// The amount of balance that is still available from the original `limit`.
-	fn available(&self) -> BalanceOf<T> {
-		self.total_deposit.available(&self.limit)
+	///
+	/// This can be used to check whether some storage can be paid for without charging it.
+	pub fn available(&self) -> BalanceOf<T> {
+		self.total_deposit
+			.saturating_add(&self.transient_deposit)
+			.available(&self.limit)
+	}
+
+	/// The limit of how much balance this meter is allowed to consume.
+	pub fn limit(&self) -> BalanceOf<T> {
+		self.limit
+	}
+
+	/// The deposit of this meter and all of its already absorbed children.
+	///
+	/// This does not include the changes recorded by this meter itself before it is absorbed.
+	pub fn total_deposit(&self) -> &DepositOf<T> {
+		&self.total_deposit
+	}
+
+	/// The weight rebate for all the storage freed within this meter and its absorbed children.
+	///
+	/// Only the storage that is freed on net is rebated. Storage that is added by one call and
+	/// removed by another call within the same meter doesn't earn any rebate. Neither does the
+	/// storage of a terminated contract. See [`Config::StorageRefundRebate`].
+	pub fn rebate(&self) -> Weight {
+		let diff = &self.storage_diff;
+		T::StorageRefundRebate::rebate(
+			diff.bytes_removed.saturating_sub(diff.bytes_added),
+			diff.items_removed.saturating_sub(diff.items_added),
+		)
+	}
+
+	/// Whether this is a [`Root`] meter.
+	pub const fn is_root() -> bool {
+		S::IS_ROOT
 	}
 
 	/// Returns the state of the currently executed contract.
@@ -360,20 +721,66 @@ where
 	T: Config,
 	E: Ext<T>,
 {
-	/// Create new storage meter for the specified `origin` and `limit`.
+	/// Same as [`Self::new_with_limit`] but with an absolute `limit`.
 	///
-	/// This tries to [`Ext::check_limit`] on `origin` and fails if this is not possible.
+	/// Only used by tests. The pallet always passes a [`Limit`] to [`Self::new_with_limit`].
+	#[cfg(test)]
 	pub fn new(
 		origin: &Origin<T>,
 		limit: Option<BalanceOf<T>>,
 		min_leftover: BalanceOf<T>,
 	) -> Result<Self, DispatchError> {
+		Self::new_with_limit(origin, limit.map(Limit::Absolute), min_leftover)
+	}
+
+	/// Same as [`Self::new_with_limit`] but the storage deposit is paid by `payer` instead of
+	/// `origin`.
+	///
+	/// The limit is checked against the balance of `payer` and [`Self::try_into_deposit`] only
+	/// ever charges `payer` and refunds to it. The existential deposit of an instantiated
+	/// contract is sent by `payer`, too. The `origin` is ignored from then on. As the
+	/// `min_leftover` is sent by `origin` it is only required to be left in `payer` if both are
+	/// the same account.
+	pub fn new_with_payer(
+		origin: &Origin<T>,
+		payer: T::AccountId,
+		limit: Option<Limit<BalanceOf<T>>>,
+		min_leftover: BalanceOf<T>,
+	) -> Result<Self, DispatchError> {
+		let min_leftover = match origin {
+			Origin::Signed(o) if *o == payer => min_leftover,
+			_ => Zero::zero(),
+		};
+		let meter =
+			Self::new_with_limit(&Origin::from_account_id(payer.clone()), limit, min_leftover)?;
+		Ok(Self { payer: Some(payer), ..meter })
+	}
+
+	/// Create new storage meter for the specified `origin` and `limit`.
+	///
+	/// This tries to [`Ext::check_limit`] on `origin` and fails if this is not possible.
+	/// An explicit `limit` of zero is rejected as such a meter can't pay for any storage. Pass
+	/// `None` in order to use whatever is available. The `limit` can also be a percentage of the
+	/// balance that `origin` can spend. For the root origin a percentage is taken of
+	/// [`Config::DefaultDepositLimit`].
+	pub fn new_with_limit(
+		origin: &Origin<T>,
+		limit: Option<Limit<BalanceOf<T>>>,
+		min_leftover: BalanceOf<T>,
+	) -> Result<Self, DispatchError> {
+		ensure!(
+			!matches!(limit, Some(limit) if limit.is_zero()),
+			<Error<T>>::ZeroStorageDepositLimit
+		);
 		// Check the limit only if the origin is not root.
 		return match origin {
-			Origin::Root => Ok(Self {
-				limit: limit.unwrap_or(T::DefaultDepositLimit::get()),
-				..Default::default()
-			}),
+			Origin::Root => {
+				let default = T::DefaultDepositLimit::get();
+				Ok(Self {
+					limit: limit.map_or(default, |limit| limit.resolve(default)),
+					..Default::default()
+				})
+			},
 			Origin::Signed(o) => {
 				let limit = E::check_limit(o, limit, min_leftover)?;
 				Ok(Self { limit, ..Default::default() })
@@ -388,18 +795,63 @@ where
 	/// This drops the root meter in order to make sure it is only called when the whole
 	/// execution did finish.
 	pub fn try_into_deposit(self, origin: &Origin<T>) -> Result<DepositOf<T>, DispatchError> {
-		// Only refund or charge deposit if the origin is not root.
-		let origin = match origin {
-			Origin::Root => return Ok(Deposit::Charge(Zero::zero())),
-			Origin::Signed(o) => o,
+		self.try_into_deposit_detailed(origin).map(|(deposit, _)| deposit)
+	}
+
+	/// Same as [`Self::try_into_deposit`] but also returns the individual deposits of every
+	/// contract in the call stack in the order they were applied.
+	///
+	/// Nothing is returned for a root origin as nothing is charged then.
+	pub fn try_into_deposit_detailed(
+		self,
+		origin: &Origin<T>,
+	) -> Result<(DepositOf<T>, Vec<(T::AccountId, DepositOf<T>)>), DispatchError> {
+		let origin = match self.deposit_account(origin) {
+			Some(account) => account.clone(),
+			None => return Ok((Deposit::Charge(Zero::zero()), Vec::new())),
 		};
-		for charge in self.charges.iter().filter(|c| matches!(c.amount, Deposit::Refund(_))) {
-			E::charge(origin, &charge.contract, &charge.amount, &charge.state)?;
+		let (refunds, charges): (Vec<_>, Vec<_>) = Self::coalesce_charges(self.charges)
+			.into_iter()
+			.partition(|c| matches!(c.amount, Deposit::Refund(_)));
+		let mut contributions = Vec::with_capacity(refunds.len() + charges.len());
+		for charge in refunds.into_iter().chain(charges) {
+			E::charge(&origin, &charge.contract, &charge.amount, &charge.state, &charge.reason)?;
+			contributions.push((charge.contract, charge.amount));
+		}
+		Ok((self.total_deposit, contributions))
+	}
+
+	/// The account that is charged and refunded when this meter is turned into a deposit.
+	///
+	/// A payer is charged regardless of the origin. Otherwise nobody is charged or refunded if
+	/// the origin is root.
+	pub fn deposit_account<'a>(&'a self, origin: &'a Origin<T>) -> Option<&'a T::AccountId> {
+		match (&self.payer, origin) {
+			(Some(payer), _) => Some(payer),
+			(None, Origin::Root) => None,
+			(None, Origin::Signed(o)) => Some(o),
 		}
-		for charge in self.charges.iter().filter(|c| matches!(c.amount, Deposit::Charge(_))) {
-			E::charge(origin, &charge.contract, &charge.amount, &charge.state)?;
+	}
+
+	/// Nets all the charges of a contract that is called more than once within the call stack
+	/// into a single charge. This way at most one transfer is made per contract.
+	///
+	/// Charges of a terminated contract are kept as they are as the termination itself needs to
+	/// be applied. The same applies to charges of different hold reasons.
+	fn coalesce_charges(charges: Vec<Charge<T>>) -> Vec<Charge<T>> {
+		let mut coalesced: Vec<Charge<T>> = Vec::with_capacity(charges.len());
+		for charge in charges {
+			let alive = |c: &Charge<T>| matches!(c.state, ContractState::Alive);
+			match coalesced.iter_mut().find(|c| {
+				alive(c) &&
+					alive(&charge) && c.contract == charge.contract &&
+					c.reason == charge.reason
+			}) {
+				Some(c) => c.amount = c.amount.saturating_add(&charge.amount),
+				None => coalesced.push(charge),
+			}
 		}
-		Ok(self.total_deposit)
+		coalesced
 	}
 }
 
@@ -417,6 +869,33 @@ where
 		};
 	}
 
+	/// Charges `diff` for transient storage from the meter.
+	///
+	/// Transient storage is discarded at the end of the transaction. Its deposit counts against
+	/// the limit of this meter and its parents during execution but it is never actually charged.
+	/// Hence it nets to zero when the root meter is turned into a deposit.
+	///
+	/// The transient deposit is not clamped at zero. Freeing transient storage that was created
+	/// by a parent or an already returned sibling frees up the limit that was consumed by them.
+	/// This can raise [`Self::available`] of this meter above its own [`Self::limit`].
+	pub fn charge_transient(&mut self, diff: &Diff) {
+		debug_assert!(matches!(self.contract_state(), ContractState::Alive));
+		let added = Diff {
+			bytes_added: diff.bytes_added,
+			items_added: diff.items_added,
+			..Default::default()
+		}
+		.update_contract::<T>(None);
+		let removed = Diff {
+			bytes_added: diff.bytes_removed,
+			items_added: diff.items_removed,
+			..Default::default()
+		}
+		.update_contract::<T>(None);
+		self.transient_deposit =
+			self.transient_deposit.saturating_add(&added).saturating_sub(&removed);
+	}
+
 	/// Adds a deposit charge.
 	///
 	/// Use this method instead of [`Self::charge`] when the charge is not the result of a storage
@@ -425,10 +904,16 @@ where
 	/// deposit charge separately from the storage charge.
 	pub fn charge_deposit(&mut self, contract: T::AccountId, amount: DepositOf<T>) {
 		self.total_deposit = self.total_deposit.saturating_add(&amount);
-		self.charges.push(Charge { contract, amount, state: ContractState::Alive });
+		self.charges.push(Charge {
+			contract,
+			amount,
+			state: ContractState::Alive,
+			reason: self.reason,
+		});
 	}
 
-	/// Charges from `origin` a storage deposit for contract instantiation.
+	/// Charges from `origin`, or the payer of this meter if any, a storage deposit for contract
+	/// instantiation.
 	///
 	/// This immediately transfers the balance in order to create the account.
 	pub fn charge_instantiate(
@@ -452,8 +937,10 @@ where
 		// contract execution does conclude and hence would lead to a double charge.
 		self.total_deposit = Deposit::Charge(ed);
 
-		// We need to make sure that the contract's account exists.
-		T::Currency::transfer(origin, contract, ed, Preservation::Preserve)?;
+		// We need to make sure that the contract's account exists. Like the rest of the deposit
+		// it is paid by the payer of the meter if there is one.
+		let payer = self.payer.as_ref().unwrap_or(origin);
+		T::Currency::transfer(payer, contract, ed, Preservation::Preserve)?;
 
 		// A consumer is added at account creation and removed it on termination, otherwise the
 		// runtime could remove the account. As long as a contract exists its account must exist.
@@ -489,10 +976,19 @@ where
 	/// used.
 	pub fn enforce_limit(
 		&mut self,
-		info: Option<&mut ContractInfo<T>>,
+		mut info: Option<&mut ContractInfo<T>>,
 	) -> Result<(), DispatchError> {
-		let deposit = self.own_contribution.update_contract(info);
-		let total_deposit = self.total_deposit.saturating_add(&deposit);
+		let deposit = self.own_contribution.update_contract(info.as_deref_mut());
+		if let Some(diff) = self.own_contribution.unaccounted_diff() {
+			self.storage_diff = self.storage_diff.saturating_add(diff);
+		}
+		if let Some(info) = info {
+			Self::ensure_within_deposit_ceiling(info, &deposit)?;
+		}
+		let total_deposit = self
+			.total_deposit
+			.saturating_add(&deposit)
+			.saturating_add(&self.transient_deposit);
 		// We don't want to override a `Terminated` with a `Checked`.
 		if matches!(self.contract_state(), ContractState::Alive) {
 			self.own_contribution = Contribution::Checked(deposit);
@@ -513,30 +1009,63 @@ where
 	) -> Result<(), DispatchError> {
 		match self.nested {
 			Nested::OwnLimit => self.enforce_limit(info),
-			Nested::DerivedLimit => Ok(()),
+			// The limit is enforced by the parent. However, the deposit ceiling of the contract
+			// needs to be checked right away. We do this on a copy of `info` as it is only
+			// updated once this meter is absorbed.
+			Nested::DerivedLimit => match (&self.own_contribution, info) {
+				(Contribution::Alive(diff), Some(info)) => {
+					let mut info = info.clone();
+					let deposit = diff.update_contract::<T>(Some(&mut info));
+					Self::ensure_within_deposit_ceiling(&info, &deposit)
+				},
+				_ => Ok(()),
+			},
 		}
 	}
+
+	/// Fails if the storage deposit of the contract with the updated `info` exceeds
+	/// [`Config::MaxDepositPerContract`].
+	///
+	/// Only the storage within the contract's child trie counts towards the ceiling. A `deposit`
+	/// that is a refund always passes in order to allow contracts above the ceiling to free
+	/// storage.
+	fn ensure_within_deposit_ceiling(
+		info: &ContractInfo<T>,
+		deposit: &DepositOf<T>,
+	) -> Result<(), DispatchError> {
+		let Some(ceiling) = T::MaxDepositPerContract::get() else { return Ok(()) };
+		ensure!(
+			matches!(deposit, Deposit::Refund(_)) || info.extra_deposit() <= ceiling,
+			<Error<T>>::ContractDepositLimitExhausted,
+		);
+		Ok(())
+	}
 }
 
 impl<T: Config> Ext<T> for ReservingExt {
 	fn check_limit(
 		origin: &T::AccountId,
-		limit: Option<BalanceOf<T>>,
+		limit: Option<Limit<BalanceOf<T>>>,
 		min_leftover: BalanceOf<T>,
 	) -> Result<BalanceOf<T>, DispatchError> {
 		// We are sending the `min_leftover` and the `min_balance` from the origin
 		// account as part of a contract call. Hence origin needs to have those left over
 		// as free balance after accounting for all deposits.
-		let max = T::Currency::reducible_balance(origin, Preservation::Preserve, Polite)
+		let reducible = T::Currency::reducible_balance(origin, Preservation::Preserve, Polite);
+		let max = reducible
 			.saturating_sub(min_leftover)
 			.saturating_sub(Pallet::<T>::min_balance());
 		let default = max.min(T::DefaultDepositLimit::get());
-		let limit = limit.unwrap_or(default);
+		let limit = limit.map_or(default, |limit| limit.resolve(max));
+		let enough_funds = limit <= max &&
+			matches!(T::Currency::can_withdraw(origin, limit), WithdrawConsequence::Success);
+		// Report separately when the origin can't even keep the `min_leftover` as otherwise it
+		// looks like the deposit itself was unaffordable.
 		ensure!(
-			limit <= max &&
-				matches!(T::Currency::can_withdraw(origin, limit), WithdrawConsequence::Success),
-			<Error<T>>::StorageDepositNotEnoughFunds,
+			enough_funds || min_leftover <= reducible,
+			<Error<T>>::StorageDepositLeftoverNotAvailable,
 		);
+		ensure!(enough_funds, <Error<T>>::StorageDepositNotEnoughFunds);
 		Ok(limit)
 	}
 
@@ -545,6 +1074,7 @@ impl<T: Config> Ext<T> for ReservingExt {
 		contract: &T::AccountId,
 		amount: &DepositOf<T>,
 		state: &ContractState<T>,
+		reason: &HoldReason,
 	) -> Result<(), DispatchError> {
 		match amount {
 			Deposit::Charge(amount) | Deposit::Refund(amount) if amount.is_zero() => return Ok(()),
@@ -552,7 +1082,7 @@ impl<T: Config> Ext<T> for ReservingExt {
 				// This could fail if the `origin` does not have enough liquidity. Ideally, though,
 				// this should have been checked before with `check_limit`.
 				T::Currency::transfer_and_hold(
-					&HoldReason::StorageDepositReserve.into(),
+					&(*reason).into(),
 					origin,
 					contract,
 					*amount,
@@ -571,15 +1101,25 @@ impl<T: Config> Ext<T> for ReservingExt {
 				);
 			},
 			Deposit::Refund(amount) => {
-				let transferred = T::Currency::transfer_on_hold(
-					&HoldReason::StorageDepositReserve.into(),
-					contract,
-					origin,
-					*amount,
-					Precision::BestEffort,
-					Restriction::Free,
-					Fortitude::Polite,
-				)?;
+				// The deposit of a contract can be spread over all storage deposit reasons. Only
+				// if the reason of the refund doesn't hold enough the other ones are consulted.
+				let reasons = sp_std::iter::once(reason)
+					.chain(HoldReason::STORAGE_DEPOSIT.iter().filter(|other| *other != reason));
+				let mut transferred = BalanceOf::<T>::zero();
+				for reason in reasons {
+					if transferred == *amount {
+						break
+					}
+					transferred = transferred.saturating_add(T::Currency::transfer_on_hold(
+						&(*reason).into(),
+						contract,
+						origin,
+						amount.saturating_sub(transferred),
+						Precision::BestEffort,
+						Restriction::Free,
+						Fortitude::Polite,
+					)?);
+				}
 
 				Pallet::<T>::deposit_event(
 					vec![T::Hashing::hash_of(&contract), T::Hashing::hash_of(&origin)],
@@ -591,14 +1131,22 @@ impl<T: Config> Ext<T> for ReservingExt {
 				);
 
 				if transferred < *amount {
-					// This should never happen, if it does it means that there is a bug in the
-					// runtime logic. In the rare case this happens we try to refund as much as we
-					// can, thus the `Precision::BestEffort`.
+					// This should only happen if the held balance was slashed. Otherwise it means
+					// that there is a bug in the runtime logic. In the rare case this happens we
+					// try to refund as much as we can, thus the `Precision::BestEffort`.
 					log::error!(
 						target: LOG_TARGET,
 						"Failed to repatriate full storage deposit {:?} from contract {:?} to origin {:?}. Transferred {:?}.",
 						amount, contract, origin, transferred,
 					);
+					Pallet::<T>::deposit_event(
+						vec![T::Hashing::hash_of(&contract)],
+						Event::StorageDepositReconciled {
+							contract: contract.clone(),
+							expected: *amount,
+							refunded: transferred,
+						},
+					);
 				}
 			},
 		}
@@ -616,6 +1164,65 @@ impl<T: Config> Ext<T> for ReservingExt {
 	}
 }
 
+/// Same as [`Saturating::saturating_add`] but reports when the addition actually saturates.
+fn saturating_accumulate<N: Saturating + CheckedAdd + Copy>(
+	context: &'static str,
+	lhs: N,
+	rhs: N,
+) -> N {
+	audit_saturation(context, || lhs.checked_add(&rhs).is_none());
+	lhs.saturating_add(rhs)
+}
+
+/// Same as [`Saturating::saturating_mul`] but reports when the multiplication actually saturates.
+fn saturating_scale<N: Saturating + CheckedMul + Copy>(context: &'static str, lhs: N, rhs: N) -> N {
+	audit_saturation(context, || lhs.checked_mul(&rhs).is_none());
+	lhs.saturating_mul(rhs)
+}
+
+/// Same as [`Deposit::saturating_add`] but reports when the addition actually saturates.
+///
+/// Only deposits of the same direction can saturate as the others cancel each other out.
+fn saturating_accumulate_deposit<T: Config>(
+	context: &'static str,
+	lhs: &DepositOf<T>,
+	rhs: &DepositOf<T>,
+) -> DepositOf<T> {
+	audit_saturation(context, || match (lhs, rhs) {
+		(Deposit::Charge(lhs), Deposit::Charge(rhs)) |
+		(Deposit::Refund(lhs), Deposit::Refund(rhs)) => lhs.checked_add(rhs).is_none(),
+		_ => false,
+	});
+	lhs.saturating_add(rhs)
+}
+
+/// Logs when a deposit calculation in `context` hit the saturation boundary.
+///
+/// A saturated deposit means that the origin is under- or overcharged. This should never happen
+/// with sane deposit prices. The check is only done in debug builds in order to keep the release
+/// path free of additional branches.
+#[inline(always)]
+fn audit_saturation(context: &'static str, saturated: impl FnOnce() -> bool) {
+	#[cfg(debug_assertions)]
+	if saturated() {
+		log::warn!(
+			target: LOG_TARGET,
+			"Storage deposit calculation saturated: {}",
+			context,
+		);
+		#[cfg(test)]
+		SATURATIONS.with(|saturations| saturations.borrow_mut().push(context));
+	}
+	#[cfg(not(debug_assertions))]
+	let _ = (context, saturated);
+}
+
+#[cfg(all(test, debug_assertions))]
+thread_local! {
+	/// Every `context` passed to [`audit_saturation`] that actually saturated.
+	static SATURATIONS: sp_std::cell::RefCell<Vec<&'static str>> = Default::default();
+}
+
 mod private {
 	pub trait Sealed {}
 	impl Sealed for super::Root {}
@@ -626,11 +1233,15 @@ mod private {
 mod tests {
 	use super::*;
 	use crate::{
-		exec::AccountIdOf,
-		tests::{Test, ALICE, BOB, CHARLIE},
+		exec::{AccountIdOf, Key},
+		tests::{
+			DepositPerByte, DepositPerItem, ExtBuilder, LargeValueSurcharge, MaxDepositPerContract,
+			StorageRebate, Test, ALICE, BOB, CHARLIE,
+		},
 	};
-	use frame_support::parameter_types;
+	use frame_support::{assert_err, assert_ok, parameter_types};
 	use pretty_assertions::assert_eq;
+	use smallvec::Array;
 
 	type TestMeter = RawMeter<Test, TestExt, Root>;
 
@@ -651,6 +1262,7 @@ mod tests {
 		contract: AccountIdOf<Test>,
 		amount: DepositOf<Test>,
 		state: ContractState<Test>,
+		reason: HoldReason,
 	}
 
 	#[derive(Default, Debug, PartialEq, Eq, Clone)]
@@ -664,15 +1276,103 @@ mod tests {
 			self.limit_checks.clear();
 			self.charges.clear();
 		}
+
+		/// Asserts that the recorded charges are consistent with each other and add up to the
+		/// `deposit` that was returned by the meter.
+		///
+		/// `held` lists the deposit held by each contract before and after the storage
+		/// changes that were metered. Every charged contract must be listed there.
+		fn assert_balanced(&self, deposit: &DepositOf<Test>, held: &[Held]) {
+			// charges are only ever made from an origin whose limit was checked
+			for charge in &self.charges {
+				assert!(
+					self.limit_checks.iter().any(|check| check.origin == charge.origin),
+					"charge from an unchecked origin: {:?}",
+					charge,
+				);
+			}
+
+			// all refunds are applied before any charge
+			let first_charge = self
+				.charges
+				.iter()
+				.position(|c| matches!(c.amount, Deposit::Charge(_)))
+				.unwrap_or(self.charges.len());
+			assert!(
+				self.charges[first_charge..]
+					.iter()
+					.all(|c| matches!(c.amount, Deposit::Charge(_))),
+				"refund after a charge: {:?}",
+				self.charges,
+			);
+
+			// a terminated contract is never charged again
+			for (i, charge) in self.charges.iter().enumerate() {
+				if matches!(charge.state, ContractState::Terminated { .. }) {
+					assert!(
+						self.charges[i + 1..].iter().all(|c| c.contract != charge.contract),
+						"charge after termination: {:?}",
+						self.charges,
+					);
+				}
+			}
+
+			// a contract is never refunded more than it holds and ends up holding exactly
+			// what its storage grew to
+			let mut balances: Vec<(AccountIdOf<Test>, BalanceOf<Test>)> = Vec::new();
+			for charge in &self.charges {
+				let entry = held
+					.iter()
+					.find(|h| h.contract == charge.contract)
+					.unwrap_or_else(|| panic!("charge without storage growth: {:?}", charge));
+				let balance = match balances.iter_mut().find(|(c, _)| c == &charge.contract) {
+					Some((_, balance)) => balance,
+					None => {
+						balances.push((charge.contract.clone(), entry.before));
+						&mut balances.last_mut().expect("just pushed; qed").1
+					},
+				};
+				*balance = match charge.amount {
+					Deposit::Charge(amount) => *balance + amount,
+					Deposit::Refund(amount) => balance
+						.checked_sub(amount)
+						.unwrap_or_else(|| panic!("refund exceeds prior charges: {:?}", charge)),
+				};
+			}
+			for (contract, balance) in &balances {
+				let entry =
+					held.iter().find(|h| &h.contract == contract).expect("checked above; qed");
+				assert_eq!(
+					*balance, entry.after,
+					"charges don't match storage growth of {:?}",
+					contract
+				);
+			}
+
+			// the ledger adds up to the deposit
+			let net = self
+				.charges
+				.iter()
+				.fold(Deposit::Charge(0), |acc, c| acc.saturating_add(&c.amount));
+			assert_eq!(&net, deposit);
+		}
+	}
+
+	/// The deposit held by `contract` before and after a metered storage change.
+	struct Held {
+		contract: AccountIdOf<Test>,
+		before: BalanceOf<Test>,
+		after: BalanceOf<Test>,
 	}
 
 	impl Ext<Test> for TestExt {
 		fn check_limit(
 			origin: &AccountIdOf<Test>,
-			limit: Option<BalanceOf<Test>>,
+			limit: Option<Limit<BalanceOf<Test>>>,
 			min_leftover: BalanceOf<Test>,
 		) -> Result<BalanceOf<Test>, DispatchError> {
-			let limit = limit.unwrap_or(42);
+			// pretend that every origin can spend 1_000
+			let limit = limit.map_or(42, |limit| limit.resolve(1_000));
 			TestExtTestValue::mutate(|ext| {
 				ext.limit_checks
 					.push(LimitCheck { origin: origin.clone(), limit, min_leftover })
@@ -685,6 +1385,7 @@ mod tests {
 			contract: &AccountIdOf<Test>,
 			amount: &DepositOf<Test>,
 			state: &ContractState<Test>,
+			reason: &HoldReason,
 		) -> Result<(), DispatchError> {
 			TestExtTestValue::mutate(|ext| {
 				ext.charges.push(Charge {
@@ -692,6 +1393,7 @@ mod tests {
 					contract: contract.clone(),
 					amount: amount.clone(),
 					state: state.clone(),
+					reason: *reason,
 				})
 			});
 			Ok(())
@@ -724,6 +1426,8 @@ mod tests {
 			storage_items: info.items,
 			storage_byte_deposit: info.bytes_deposit,
 			storage_item_deposit: info.items_deposit,
+			storage_large_value_bytes: 0,
+			storage_large_value_deposit: Default::default(),
 			storage_base_deposit: Default::default(),
 			delegate_dependencies: Default::default(),
 		}
@@ -744,6 +1448,25 @@ mod tests {
 		)
 	}
 
+	#[test]
+	fn new_rejects_zero_limit() {
+		clear_ext();
+
+		assert_eq!(
+			TestMeter::new(&Origin::from_account_id(ALICE), Some(0), 0).unwrap_err(),
+			<Error<Test>>::ZeroStorageDepositLimit.into(),
+		);
+		assert_eq!(
+			TestMeter::new(&Origin::Root, Some(0), 0).unwrap_err(),
+			<Error<Test>>::ZeroStorageDepositLimit.into(),
+		);
+		assert!(TestExtTestValue::get().limit_checks.is_empty());
+
+		// no limit still means to use whatever is available
+		let meter = TestMeter::new(&Origin::from_account_id(ALICE), None, 0).unwrap();
+		assert_eq!(meter.available(), 42);
+	}
+
 	#[test]
 	fn empty_charge_works() {
 		clear_ext();
@@ -777,20 +1500,16 @@ mod tests {
 						Charge {
 							origin: ALICE,
 							contract: CHARLIE,
-							amount: Deposit::Refund(10),
-							state: ContractState::Alive,
-						},
-						Charge {
-							origin: ALICE,
-							contract: CHARLIE,
-							amount: Deposit::Refund(20),
+							amount: Deposit::Refund(30),
 							state: ContractState::Alive,
+							reason: HoldReason::StorageDepositReserve,
 						},
 						Charge {
 							origin: ALICE,
 							contract: BOB,
 							amount: Deposit::Charge(2),
 							state: ContractState::Alive,
+							reason: HoldReason::StorageDepositReserve,
 						},
 					],
 				},
@@ -814,12 +1533,14 @@ mod tests {
 				bytes_deposit: 100,
 				items_deposit: 10,
 			});
+			let nested0_before = nested0_info.extra_deposit();
 			let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
 			nested0.charge(&Diff {
 				bytes_added: 108,
 				bytes_removed: 5,
 				items_added: 1,
 				items_removed: 2,
+				..Default::default()
 			});
 			nested0.charge(&Diff { bytes_removed: 99, ..Default::default() });
 
@@ -829,6 +1550,7 @@ mod tests {
 				bytes_deposit: 100,
 				items_deposit: 20,
 			});
+			let nested1_before = nested1_info.extra_deposit();
 			let mut nested1 = nested0.nested(BalanceOf::<Test>::zero());
 			nested1.charge(&Diff { items_removed: 5, ..Default::default() });
 			nested0.absorb(nested1, &CHARLIE, Some(&mut nested1_info));
@@ -839,6 +1561,7 @@ mod tests {
 				bytes_deposit: 100,
 				items_deposit: 20,
 			});
+			let nested2_before = nested2_info.extra_deposit();
 			let mut nested2 = nested0.nested(BalanceOf::<Test>::zero());
 			nested2.charge(&Diff { items_removed: 7, ..Default::default() });
 			nested0.absorb(nested2, &CHARLIE, Some(&mut nested2_info));
@@ -847,6 +1570,23 @@ mod tests {
 			meter.absorb(nested0, &BOB, Some(&mut nested0_info));
 
 			assert_eq!(meter.try_into_deposit(&test_case.origin).unwrap(), test_case.deposit);
+			TestExtTestValue::get().assert_balanced(
+				&test_case.deposit,
+				&[
+					Held {
+						contract: BOB,
+						before: nested0_before,
+						after: nested0_info.extra_deposit(),
+					},
+					// `CHARLIE` is called twice and its charges are netted. Hence it holds what
+					// both of its infos hold together.
+					Held {
+						contract: CHARLIE,
+						before: nested1_before + nested2_before,
+						after: nested1_info.extra_deposit() + nested2_info.extra_deposit(),
+					},
+				],
+			);
 
 			assert_eq!(nested0_info.extra_deposit(), 112);
 			assert_eq!(nested1_info.extra_deposit(), 110);
@@ -870,12 +1610,14 @@ mod tests {
 							contract: CHARLIE,
 							amount: Deposit::Refund(119),
 							state: ContractState::Terminated { beneficiary: CHARLIE },
+							reason: HoldReason::StorageDepositReserve,
 						},
 						Charge {
 							origin: ALICE,
 							contract: BOB,
 							amount: Deposit::Charge(12),
 							state: ContractState::Alive,
+							reason: HoldReason::StorageDepositReserve,
 						},
 					],
 				},
@@ -893,12 +1635,16 @@ mod tests {
 			let mut meter = TestMeter::new(&test_case.origin, Some(1_000), 0).unwrap();
 			assert_eq!(meter.available(), 1_000);
 
+			let mut nested0_info =
+				new_info(StorageInfo { bytes: 0, items: 0, bytes_deposit: 0, items_deposit: 0 });
+			let nested0_before = nested0_info.extra_deposit();
 			let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
 			nested0.charge(&Diff {
 				bytes_added: 5,
 				bytes_removed: 1,
 				items_added: 3,
 				items_removed: 1,
+				..Default::default()
 			});
 			nested0.charge(&Diff { items_added: 2, ..Default::default() });
 
@@ -908,6 +1654,7 @@ mod tests {
 				bytes_deposit: 100,
 				items_deposit: 20,
 			});
+			let nested1_before = nested1_info.total_deposit();
 			let mut nested1 = nested0.nested(BalanceOf::<Test>::zero());
 			nested1.charge(&Diff { items_removed: 5, ..Default::default() });
 			nested1.charge(&Diff { bytes_added: 20, ..Default::default() });
@@ -915,10 +1662,890 @@ mod tests {
 			nested0.enforce_limit(Some(&mut nested1_info)).unwrap();
 			nested0.absorb(nested1, &CHARLIE, None);
 
-			meter.absorb(nested0, &BOB, None);
+			meter.absorb(nested0, &BOB, Some(&mut nested0_info));
 			assert_eq!(meter.try_into_deposit(&test_case.origin).unwrap(), test_case.deposit);
+			TestExtTestValue::get().assert_balanced(
+				&test_case.deposit,
+				&[
+					Held {
+						contract: BOB,
+						before: nested0_before,
+						after: nested0_info.extra_deposit(),
+					},
+					// a terminated contract holds nothing anymore
+					Held { contract: CHARLIE, before: nested1_before, after: 0 },
+				],
+			);
 
 			assert_eq!(TestExtTestValue::get(), test_case.expected)
 		}
 	}
+
+	#[test]
+	fn clear_all_refunds_like_termination() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+		info.storage_base_deposit = Pallet::<Test>::min_balance() + 30;
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.terminate(&info, CHARLIE);
+		meter.absorb(nested0, &BOB, None);
+
+		let deposit = Diff::clear_all(&info).update_contract::<Test>(Some(&mut info));
+
+		// termination additionally refunds the base deposit minus the existential deposit
+		assert_eq!(deposit, Deposit::Refund(120));
+		assert_eq!(meter.total_deposit(), &deposit.saturating_add(&Deposit::Refund(30)));
+		assert_eq!(info.storage_bytes, 0);
+		assert_eq!(info.storage_items, 0);
+		assert_eq!(info.extra_deposit(), 0);
+	}
+
+	#[test]
+	fn items_only_refund_leaves_bytes_untouched() {
+		let mut info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+
+		let deposit = Diff { items_removed: 5, ..Default::default() }
+			.update_contract::<Test>(Some(&mut info));
+
+		assert_eq!(deposit, Deposit::Refund(10));
+		assert_eq!(info.storage_items, 5);
+		assert_eq!(info.storage_item_deposit, 10);
+		assert_eq!(info.storage_bytes, 100);
+		assert_eq!(info.storage_byte_deposit, 100);
+	}
+
+	#[test]
+	fn bytes_only_refund_leaves_items_untouched() {
+		let mut info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+
+		let deposit = Diff { bytes_removed: 25, ..Default::default() }
+			.update_contract::<Test>(Some(&mut info));
+
+		assert_eq!(deposit, Deposit::Refund(25));
+		assert_eq!(info.storage_bytes, 75);
+		assert_eq!(info.storage_byte_deposit, 75);
+		assert_eq!(info.storage_items, 10);
+		assert_eq!(info.storage_item_deposit, 20);
+	}
+
+	#[test]
+	fn nested_with_reason_works() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 5, ..Default::default() });
+
+		let mut nested1 = nested0
+			.nested_with_reason(BalanceOf::<Test>::zero(), HoldReason::DelegateCallDepositReserve)
+			.unwrap();
+		nested1.charge(&Diff { items_added: 1, ..Default::default() });
+
+		// a plain nested meter inherits the reason of its parent
+		assert_eq!(
+			nested1.nested(BalanceOf::<Test>::zero()).reason,
+			HoldReason::DelegateCallDepositReserve
+		);
+
+		// only storage deposit reasons are accepted
+		assert_err!(
+			nested1
+				.nested_with_reason(BalanceOf::<Test>::zero(), HoldReason::CodeUploadDepositReserve)
+				.map(|_| ()),
+			<Error<Test>>::InvalidStorageDepositReason
+		);
+
+		nested0.absorb(nested1, &CHARLIE, None);
+		meter.absorb(nested0, &BOB, None);
+		meter.try_into_deposit(&Origin::from_account_id(ALICE)).unwrap();
+
+		assert_eq!(
+			TestExtTestValue::get().charges,
+			vec![
+				Charge {
+					origin: ALICE,
+					contract: CHARLIE,
+					amount: Deposit::Charge(2),
+					state: ContractState::Alive,
+					reason: HoldReason::DelegateCallDepositReserve,
+				},
+				Charge {
+					origin: ALICE,
+					contract: BOB,
+					amount: Deposit::Charge(5),
+					state: ContractState::Alive,
+					reason: HoldReason::StorageDepositReserve,
+				},
+			]
+		)
+	}
+
+	#[test]
+	fn debug_output_works() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 5, ..Default::default() });
+		meter.absorb(nested0, &BOB, None);
+
+		let output = format!("{:?}", meter);
+		assert!(output.contains("total_deposit: Charge(5)"));
+		assert!(output.contains("available: 995"));
+		assert!(output.contains("charges: [Charge { contract: "));
+		assert!(output.contains("amount: Charge(5), state: Alive, reason: StorageDepositReserve"));
+		assert!(output.contains("rebate: "));
+		assert!(output.contains("payer: None"));
+	}
+
+	#[test]
+	fn transient_charges_count_against_limit_but_are_never_charged() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(100), 0).unwrap();
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge_transient(&Diff { bytes_added: 60, ..Default::default() });
+		nested0.enforce_limit(None).unwrap();
+		meter.absorb(nested0, &BOB, None);
+		assert_eq!(meter.available(), 40);
+
+		// persistent storage does not fit next to the transient storage
+		let mut nested1 = meter.nested(BalanceOf::<Test>::zero());
+		nested1.charge(&Diff { bytes_added: 50, ..Default::default() });
+		assert_err!(nested1.enforce_limit(None), <Error<Test>>::StorageDepositLimitExhausted);
+
+		// freeing transient storage frees up the limit again
+		let mut nested2 = meter.nested(BalanceOf::<Test>::zero());
+		nested2.charge_transient(&Diff { bytes_removed: 20, ..Default::default() });
+		assert_eq!(nested2.limit(), 40);
+		assert_eq!(nested2.available(), 60);
+		nested2.charge(&Diff { bytes_added: 50, ..Default::default() });
+		nested2.enforce_limit(None).unwrap();
+		meter.absorb(nested2, &CHARLIE, None);
+		assert_eq!(meter.available(), 10);
+
+		assert_eq!(
+			meter.try_into_deposit(&Origin::from_account_id(ALICE)).unwrap(),
+			Deposit::Charge(50)
+		);
+		assert_eq!(
+			TestExtTestValue::get().charges,
+			vec![Charge {
+				origin: ALICE,
+				contract: CHARLIE,
+				amount: Deposit::Charge(50),
+				state: ContractState::Alive,
+				reason: HoldReason::StorageDepositReserve,
+			}]
+		)
+	}
+
+	#[test]
+	fn split_at_limit_works() {
+		// a charge over the limit is clamped and overshoots by the excess
+		assert_eq!(DepositOf::<Test>::Charge(150).split_at_limit(&100), (Deposit::Charge(100), 50));
+		// a charge within the limit is returned as is
+		assert_eq!(DepositOf::<Test>::Charge(80).split_at_limit(&100), (Deposit::Charge(80), 0));
+		assert_eq!(DepositOf::<Test>::Charge(100).split_at_limit(&100), (Deposit::Charge(100), 0));
+		// a refund always fits
+		assert_eq!(DepositOf::<Test>::Refund(150).split_at_limit(&100), (Deposit::Refund(150), 0));
+	}
+
+	#[test]
+	fn large_value_surcharge_applies_to_excess_only() {
+		LargeValueSurcharge::set((10, 3));
+		ExtBuilder::default().build().execute_with(|| {
+			let mut info = new_info(StorageInfo::default());
+			let meter = Meter::<Test>::new(&Origin::Root, None, 0).unwrap();
+			let mut write = |key: u8, value: Option<Vec<u8>>| {
+				let mut nested = meter.nested(BalanceOf::<Test>::zero());
+				info.write(&Key::Fix([key; 32]), value, Some(&mut nested), false).unwrap();
+				nested.enforce_limit(Some(&mut info)).unwrap();
+				(info.storage_byte_deposit, info.storage_large_value_deposit)
+			};
+
+			// a value under the threshold is not surcharged
+			assert_eq!(write(1, Some(vec![0; 10])), (10, 0));
+
+			// only the 40 bytes over the threshold are surcharged
+			assert_eq!(write(2, Some(vec![0; 50])), (10 + 50, 40 * 3));
+
+			// growing a value that is already over the threshold only surcharges the growth
+			assert_eq!(write(2, Some(vec![0; 90])), (10 + 90, 80 * 3));
+
+			// shrinking it refunds the surcharge of the bytes that no longer exceed the threshold
+			assert_eq!(write(2, Some(vec![0; 30])), (10 + 30, 20 * 3));
+
+			// removing a small value refunds nothing of the surcharge
+			assert_eq!(write(1, None), (30, 20 * 3));
+
+			// removing the large value refunds all of the surcharge
+			assert_eq!(write(2, None), (0, 0));
+			assert_eq!(info.storage_large_value_bytes, 0);
+		});
+	}
+
+	#[test]
+	fn large_value_written_and_removed_in_one_frame_costs_nothing() {
+		LargeValueSurcharge::set((10, 3));
+		ExtBuilder::default().build().execute_with(|| {
+			let mut info = new_info(StorageInfo::default());
+			let meter = Meter::<Test>::new(&Origin::Root, None, 0).unwrap();
+
+			let mut nested = meter.nested(BalanceOf::<Test>::zero());
+			let key = Key::Fix([1; 32]);
+			info.write(&key, Some(vec![0; 15]), Some(&mut nested), false).unwrap();
+			info.write(&key, None, Some(&mut nested), false).unwrap();
+			nested.enforce_limit(Some(&mut info)).unwrap();
+
+			assert_eq!(info.storage_byte_deposit, 0);
+			assert_eq!(info.storage_large_value_bytes, 0);
+			assert_eq!(info.storage_large_value_deposit, 0);
+		});
+	}
+
+	#[test]
+	fn deep_nested_meter_stack_settles() {
+		// One meter for every frame of the deepest possible call stack.
+		let depth_limit = <<Test as Config>::CallStack as Array>::size() as u32 + 1;
+		// Every fourth frame does not change any storage.
+		let bytes_added = |depth: u32| if depth % 4 == 0 { 0 } else { depth };
+		let contract = |depth: u32| <AccountIdOf<Test>>::new([depth as u8; 32]);
+
+		clear_ext();
+
+		let mut meter =
+			TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000_000), 0).unwrap();
+		let mut stack = vec![meter.nested(BalanceOf::<Test>::zero())];
+		for _ in 1..depth_limit {
+			let child = stack.last().unwrap().nested(BalanceOf::<Test>::zero());
+			stack.push(child);
+		}
+		for (depth, frame) in stack.iter_mut().enumerate() {
+			frame.charge(&Diff { bytes_added: bytes_added(depth as u32), ..Default::default() });
+		}
+
+		// absorb bottom-up just like the call stack unwinds
+		while let Some(child) = stack.pop() {
+			let depth = stack.len() as u32;
+			match stack.last_mut() {
+				Some(parent) => parent.absorb(child, &contract(depth), None),
+				None => meter.absorb(child, &contract(depth), None),
+			}
+		}
+
+		let expected: u64 = (0..depth_limit).map(|depth| u64::from(bytes_added(depth))).sum();
+		let non_zero_frames = (0..depth_limit).filter(|depth| bytes_added(*depth) != 0).count();
+		assert_eq!(meter.total_deposit, Deposit::Charge(expected));
+		assert_eq!(meter.charges.len(), non_zero_frames);
+
+		assert_eq!(
+			meter.try_into_deposit(&Origin::from_account_id(ALICE)).unwrap(),
+			Deposit::Charge(expected)
+		);
+		assert_eq!(TestExtTestValue::get().charges.len(), non_zero_frames);
+	}
+
+	#[test]
+	fn storage_rebate_only_for_freed_storage() {
+		StorageRebate::set((Weight::from_parts(10, 1), Weight::from_parts(100, 0)));
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+
+		// adding storage does not produce a rebate
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 20, items_added: 2, ..Default::default() });
+		meter.absorb(nested0, &BOB, Some(&mut info));
+		assert_eq!(meter.rebate(), Weight::zero());
+
+		// freeing storage does
+		let mut nested1 = meter.nested(BalanceOf::<Test>::zero());
+		nested1.charge(&Diff { bytes_removed: 30, items_removed: 1, ..Default::default() });
+		nested1.enforce_limit(Some(&mut info)).unwrap();
+		assert_eq!(nested1.rebate(), Weight::from_parts(400, 30));
+
+		// but only what is freed on net within the whole meter is rebated
+		meter.absorb(nested1, &BOB, Some(&mut info));
+		assert_eq!(meter.rebate(), Weight::from_parts(100, 10));
+	}
+
+	#[test]
+	fn storage_rebate_is_not_given_for_storage_that_is_added_again() {
+		StorageRebate::set((Weight::from_parts(10, 1), Weight::from_parts(100, 0)));
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut info = new_info(StorageInfo::default());
+
+		// storage written by one call and removed by a later one
+		for diff in [
+			Diff { bytes_added: 50, items_added: 5, ..Default::default() },
+			Diff { bytes_removed: 50, items_removed: 5, ..Default::default() },
+		] {
+			let mut nested = meter.nested(BalanceOf::<Test>::zero());
+			nested.charge(&diff);
+			nested.enforce_limit(Some(&mut info)).unwrap();
+			meter.absorb(nested, &BOB, Some(&mut info));
+		}
+
+		assert_eq!(meter.total_deposit(), &Deposit::Charge(0));
+		assert_eq!(meter.rebate(), Weight::zero());
+	}
+
+	#[test]
+	fn enforce_limit_does_not_override_termination() {
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.terminate(&info, CHARLIE);
+		nested0.enforce_limit(None).unwrap();
+
+		assert!(matches!(
+			nested0.own_contribution,
+			Contribution::Terminated { deposit: Deposit::Refund(119), .. }
+		));
+		assert_eq!(nested0.contract_state(), ContractState::Terminated { beneficiary: CHARLIE });
+
+		meter.absorb(nested0, &BOB, None);
+		assert_eq!(meter.total_deposit, Deposit::Refund(119));
+	}
+
+	#[test]
+	fn terminate_after_enforce_limit_overrides_checked() {
+		let meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 10, ..Default::default() });
+		nested0.enforce_limit(None).unwrap();
+		assert!(matches!(nested0.own_contribution, Contribution::Checked(Deposit::Charge(10))));
+
+		// the checked storage changes are refunded together with everything else
+		nested0.terminate(&info, CHARLIE);
+		assert!(matches!(
+			nested0.own_contribution,
+			Contribution::Terminated { deposit: Deposit::Refund(119), .. }
+		));
+	}
+
+	#[test]
+	#[should_panic(expected = "Charge is never called after termination")]
+	fn charge_after_termination_panics() {
+		let meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let info = new_info(StorageInfo::default());
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.terminate(&info, CHARLIE);
+		nested0.charge(&Diff { bytes_added: 10, ..Default::default() });
+	}
+
+	#[test]
+	#[should_panic(expected = "Charge is never called after termination")]
+	fn charge_after_enforce_limit_panics() {
+		let meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.enforce_limit(None).unwrap();
+		nested0.charge(&Diff { bytes_added: 10, ..Default::default() });
+	}
+
+	#[test]
+	fn charge_saturates_with_large_deposits_per_unit() {
+		DepositPerByte::set(u64::MAX / 4);
+		DepositPerItem::set(u64::MAX / 4);
+		clear_ext();
+
+		// a single product overflows
+		assert_eq!(
+			Diff { bytes_added: 5, ..Default::default() }.update_contract::<Test>(None),
+			Deposit::Charge(u64::MAX)
+		);
+		// both products fit but their sum overflows
+		assert_eq!(
+			Diff { bytes_added: 3, items_added: 2, ..Default::default() }
+				.update_contract::<Test>(None),
+			Deposit::Charge(u64::MAX)
+		);
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(u64::MAX), 0).unwrap();
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 3, ..Default::default() });
+		nested0.enforce_limit(None).unwrap();
+		meter.absorb(nested0, &BOB, None);
+		assert_eq!(meter.available(), u64::MAX - u64::MAX / 4 * 3);
+
+		// the second frame does not fit into what is left of the limit
+		let mut nested1 = meter.nested(BalanceOf::<Test>::zero());
+		nested1.charge(&Diff { items_added: 2, ..Default::default() });
+		assert_err!(nested1.enforce_limit(None), <Error<Test>>::StorageDepositLimitExhausted);
+
+		// absorbing it anyways saturates the total deposit instead of overflowing
+		meter.absorb(nested1, &CHARLIE, None);
+		assert_eq!(meter.available(), 0);
+		assert_eq!(
+			meter.try_into_deposit(&Origin::from_account_id(ALICE)).unwrap(),
+			Deposit::Charge(u64::MAX)
+		);
+	}
+
+	#[test]
+	fn state_predicate_works() {
+		const _: () = assert!(TestMeter::is_root());
+		assert!(!RawMeter::<Test, TestExt, Nested>::is_root());
+	}
+
+	#[cfg(debug_assertions)]
+	fn take_saturations() -> Vec<&'static str> {
+		SATURATIONS.with(|saturations| saturations.take())
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	fn saturation_is_reported() {
+		DepositPerByte::set(u64::MAX / 4);
+		DepositPerItem::set(u64::MAX / 4);
+		take_saturations();
+		clear_ext();
+
+		// deposits that fit are not reported
+		Diff { bytes_added: 3, items_added: 1, ..Default::default() }.update_contract::<Test>(None);
+		assert!(take_saturations().is_empty());
+
+		// a product and a sum that overflow
+		Diff { bytes_added: 5, ..Default::default() }.update_contract::<Test>(None);
+		assert_eq!(take_saturations(), vec!["bytes deposit"]);
+		Diff { bytes_added: 3, items_added: 2, ..Default::default() }.update_contract::<Test>(None);
+		assert_eq!(take_saturations(), vec!["storage deposit"]);
+
+		// absorbing more than fits into the total deposit
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(u64::MAX), 0).unwrap();
+		for contract in [BOB, CHARLIE] {
+			let mut nested = meter.nested(BalanceOf::<Test>::zero());
+			nested.charge(&Diff { bytes_added: 3, ..Default::default() });
+			meter.absorb(nested, &contract, None);
+		}
+		assert_eq!(take_saturations(), vec!["absorbed deposit"]);
+	}
+
+	#[test]
+	fn reverted_child_contributes_nothing() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 5, ..Default::default() });
+		let mut nested1 = nested0.nested(BalanceOf::<Test>::zero());
+		nested1.charge(&Diff { bytes_removed: 50, items_removed: 5, ..Default::default() });
+		nested0.absorb(nested1, &CHARLIE, Some(&mut info));
+		meter.absorb_reverted(nested0);
+
+		assert_eq!(meter.available(), 1_000);
+		assert_eq!(meter.rebate(), Weight::zero());
+		assert_eq!(
+			meter.try_into_deposit(&Origin::from_account_id(ALICE)).unwrap(),
+			Deposit::Charge(0)
+		);
+		assert_eq!(TestExtTestValue::get().charges, vec![]);
+	}
+
+	#[test]
+	fn available_and_limit_work() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		assert_eq!((meter.limit(), meter.available()), (1_000, 1_000));
+
+		let mut nested0 = meter.nested(100);
+		nested0.charge(&Diff { bytes_added: 5, ..Default::default() });
+		assert_eq!((nested0.limit(), nested0.available()), (100, 100));
+		nested0.enforce_limit(None).unwrap();
+		meter.absorb(nested0, &BOB, None);
+
+		// the limit stays the same while what is available shrinks
+		assert_eq!((meter.limit(), meter.available()), (1_000, 995));
+	}
+
+	#[test]
+	fn try_into_deposit_detailed_works() {
+		for (origin, expected) in [
+			(
+				Origin::<Test>::from_account_id(ALICE),
+				(
+					Deposit::Refund(45),
+					vec![(CHARLIE, Deposit::Refund(50)), (BOB, Deposit::Charge(5))],
+				),
+			),
+			(Origin::<Test>::Root, (Deposit::Charge(0), vec![])),
+		] {
+			clear_ext();
+
+			let mut meter = TestMeter::new(&origin, Some(1_000), 0).unwrap();
+
+			let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+			nested0.charge(&Diff { bytes_added: 5, ..Default::default() });
+			meter.absorb(nested0, &BOB, None);
+
+			let mut info = new_info(StorageInfo {
+				bytes: 100,
+				items: 10,
+				bytes_deposit: 100,
+				items_deposit: 20,
+			});
+			let mut nested1 = meter.nested(BalanceOf::<Test>::zero());
+			nested1.charge(&Diff { bytes_removed: 50, ..Default::default() });
+			meter.absorb(nested1, &CHARLIE, Some(&mut info));
+
+			assert_eq!(meter.try_into_deposit_detailed(&origin).unwrap(), expected);
+		}
+	}
+
+	#[test]
+	fn max_deposit_per_contract_is_enforced() {
+		MaxDepositPerContract::set(Some(10));
+		clear_ext();
+
+		let meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut info = new_info(StorageInfo::default());
+
+		// a sub call without its own limit is checked against the ceiling without updating `info`
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 10, ..Default::default() });
+		assert_ok!(nested0.enforce_subcall_limit(Some(&mut info)));
+		assert_eq!(info.extra_deposit(), 0);
+		nested0.charge(&Diff { bytes_added: 1, ..Default::default() });
+		assert_err!(
+			nested0.enforce_subcall_limit(Some(&mut info)),
+			<Error<Test>>::ContractDepositLimitExhausted
+		);
+
+		// the absolute deposit of the contract is checked, not the one of the call
+		let mut nested1 = meter.nested(BalanceOf::<Test>::zero());
+		nested1.charge(&Diff { bytes_added: 10, ..Default::default() });
+		assert_ok!(nested1.enforce_limit(Some(&mut info)));
+		assert_eq!(info.extra_deposit(), 10);
+		let mut nested2 = meter.nested(BalanceOf::<Test>::zero());
+		nested2.charge(&Diff { bytes_added: 1, ..Default::default() });
+		assert_err!(
+			nested2.enforce_limit(Some(&mut info)),
+			<Error<Test>>::ContractDepositLimitExhausted
+		);
+
+		// freeing storage is always possible
+		MaxDepositPerContract::set(Some(5));
+		let mut nested3 = meter.nested(BalanceOf::<Test>::zero());
+		nested3.charge(&Diff { bytes_removed: 2, ..Default::default() });
+		assert_ok!(nested3.enforce_limit(Some(&mut info)));
+	}
+
+	#[test]
+	fn charges_to_the_same_contract_are_coalesced() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+		info.storage_base_deposit = Pallet::<Test>::min_balance() + 10;
+
+		fn charge(
+			meter: &mut TestMeter,
+			diff: Diff,
+			contract: AccountIdOf<Test>,
+			info: Option<&mut ContractInfo<Test>>,
+		) {
+			let mut nested = meter.nested(BalanceOf::<Test>::zero());
+			nested.charge(&diff);
+			meter.absorb(nested, &contract, info);
+		}
+		charge(&mut meter, Diff { bytes_added: 5, ..Default::default() }, BOB, None);
+		charge(
+			&mut meter,
+			Diff { bytes_removed: 10, ..Default::default() },
+			CHARLIE,
+			Some(&mut info),
+		);
+		charge(&mut meter, Diff { bytes_added: 3, ..Default::default() }, BOB, None);
+
+		// a different hold reason is not coalesced
+		let mut nested = meter
+			.nested_with_reason(BalanceOf::<Test>::zero(), HoldReason::DelegateCallDepositReserve)
+			.unwrap();
+		nested.charge(&Diff { bytes_added: 1, ..Default::default() });
+		meter.absorb(nested, &BOB, None);
+
+		// a termination is not coalesced
+		let mut nested = meter.nested(BalanceOf::<Test>::zero());
+		nested.terminate(&info, ALICE);
+		meter.absorb(nested, &CHARLIE, None);
+
+		assert_eq!(
+			meter.try_into_deposit(&Origin::from_account_id(ALICE)).unwrap(),
+			Deposit::Refund(121)
+		);
+		assert_eq!(
+			TestExtTestValue::get().charges,
+			vec![
+				Charge {
+					origin: ALICE,
+					contract: CHARLIE,
+					amount: Deposit::Refund(10),
+					state: ContractState::Alive,
+					reason: HoldReason::StorageDepositReserve,
+				},
+				Charge {
+					origin: ALICE,
+					contract: CHARLIE,
+					amount: Deposit::Refund(120),
+					state: ContractState::Terminated { beneficiary: ALICE },
+					reason: HoldReason::StorageDepositReserve,
+				},
+				Charge {
+					origin: ALICE,
+					contract: BOB,
+					amount: Deposit::Charge(8),
+					state: ContractState::Alive,
+					reason: HoldReason::StorageDepositReserve,
+				},
+				Charge {
+					origin: ALICE,
+					contract: BOB,
+					amount: Deposit::Charge(1),
+					state: ContractState::Alive,
+					reason: HoldReason::DelegateCallDepositReserve,
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn total_deposit_works() {
+		clear_ext();
+
+		let mut meter = TestMeter::new(&Origin::from_account_id(ALICE), Some(1_000), 0).unwrap();
+		let mut info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+
+		let mut nested0 = meter.nested(BalanceOf::<Test>::zero());
+		nested0.charge(&Diff { bytes_added: 5, ..Default::default() });
+		assert_eq!(nested0.total_deposit(), &Deposit::Charge(0));
+		meter.absorb(nested0, &BOB, None);
+		assert_eq!(meter.total_deposit(), &Deposit::Charge(5));
+
+		let mut nested1 = meter.nested(BalanceOf::<Test>::zero());
+		nested1.charge(&Diff { bytes_removed: 10, ..Default::default() });
+		meter.absorb(nested1, &CHARLIE, Some(&mut info));
+		assert_eq!(meter.total_deposit(), &Deposit::Refund(5));
+	}
+
+	#[test]
+	fn percentage_limit_works() {
+		clear_ext();
+
+		let meter = TestMeter::new_with_limit(
+			&Origin::from_account_id(ALICE),
+			Some(Limit::Percent(Permill::from_percent(10))),
+			0,
+		)
+		.unwrap();
+		assert_eq!(meter.limit(), 100);
+		assert_eq!(
+			TestExtTestValue::get().limit_checks,
+			vec![LimitCheck { origin: ALICE, limit: 100, min_leftover: 0 }]
+		);
+
+		assert_eq!(
+			TestMeter::new_with_limit(
+				&Origin::from_account_id(ALICE),
+				Some(Limit::Percent(Permill::zero())),
+				0
+			)
+			.unwrap_err(),
+			<Error<Test>>::ZeroStorageDepositLimit.into(),
+		);
+	}
+
+	#[test]
+	fn percentage_limit_is_resolved_against_spendable_balance() {
+		ExtBuilder::default().existential_deposit(100).build().execute_with(|| {
+			// the existential deposit needs to stay in the account and another one is sent
+			// along with the call
+			let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_200 + 50);
+			let limit = Some(Limit::Percent(Permill::from_percent(10)));
+
+			let meter =
+				Meter::<Test>::new_with_limit(&Origin::from_account_id(ALICE), limit, 50).unwrap();
+			assert_eq!(meter.limit(), 100);
+
+			// all of the spendable balance can be used
+			let limit = Some(Limit::Percent(Permill::one()));
+			let meter =
+				Meter::<Test>::new_with_limit(&Origin::from_account_id(ALICE), limit, 50).unwrap();
+			assert_eq!(meter.limit(), 1_000);
+		});
+	}
+
+	#[test]
+	fn payer_is_charged_instead_of_origin() {
+		for origin in [Origin::<Test>::from_account_id(ALICE), Origin::<Test>::Root] {
+			clear_ext();
+
+			let mut meter =
+				TestMeter::new_with_payer(&origin, CHARLIE, Some(Limit::Absolute(1_000)), 50)
+					.unwrap();
+			let mut nested = meter.nested(BalanceOf::<Test>::zero());
+			nested.charge(&Diff { bytes_added: 5, items_added: 1, ..Default::default() });
+			meter.absorb(nested, &BOB, None);
+
+			assert_eq!(meter.try_into_deposit(&origin).unwrap(), Deposit::Charge(7));
+			assert_eq!(
+				TestExtTestValue::get(),
+				TestExt {
+					// the origin's min leftover doesn't apply to the payer
+					limit_checks: vec![LimitCheck {
+						origin: CHARLIE,
+						limit: 1_000,
+						min_leftover: 0
+					}],
+					charges: vec![Charge {
+						origin: CHARLIE,
+						contract: BOB,
+						amount: Deposit::Charge(7),
+						state: ContractState::Alive,
+						reason: HoldReason::StorageDepositReserve,
+					}],
+				}
+			);
+		}
+
+		// the min leftover is kept if the origin pays for itself
+		clear_ext();
+		TestMeter::new_with_payer(
+			&Origin::from_account_id(ALICE),
+			ALICE,
+			Some(Limit::Absolute(1_000)),
+			50,
+		)
+		.unwrap();
+		assert_eq!(
+			TestExtTestValue::get().limit_checks,
+			vec![LimitCheck { origin: ALICE, limit: 1_000, min_leftover: 50 }]
+		);
+	}
+
+	#[test]
+	fn payer_pays_for_instantiation() {
+		ExtBuilder::default().existential_deposit(5).build().execute_with(|| {
+			let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+			let _ = <Test as Config>::Currency::set_balance(&CHARLIE, 1_000_000);
+			let origin = Origin::from_account_id(ALICE);
+
+			let mut meter = Meter::<Test>::new_with_payer(&origin, CHARLIE, None, 0).unwrap();
+			let mut nested = meter.nested(BalanceOf::<Test>::zero());
+			let mut info = ContractInfo::<Test>::new(&BOB, 0, Default::default()).unwrap();
+			let deposit = nested
+				.charge_instantiate(&ALICE, &BOB, &mut info, &CodeInfo::new(ALICE))
+				.unwrap();
+			meter.absorb(nested, &BOB, Some(&mut info));
+			assert_eq!(meter.try_into_deposit(&origin).unwrap(), deposit);
+
+			// the existential deposit as well as the held deposit are paid by the payer
+			let deposit = deposit.charge_or_zero();
+			assert_eq!(<Test as Config>::Currency::total_balance(&ALICE), 1_000_000);
+			assert_eq!(<Test as Config>::Currency::total_balance(&CHARLIE), 1_000_000 - deposit);
+			assert_eq!(<Test as Config>::Currency::total_balance(&BOB), deposit);
+		});
+	}
+
+	#[test]
+	fn diff_builder_works() {
+		let diff = Diff::new()
+			.with_bytes_added(10)
+			.with_bytes_removed(3)
+			.with_items_added(2)
+			.with_items_removed(1)
+			.with_large_value_bytes_added(4)
+			.with_large_value_bytes_removed(2)
+			.with_bytes_added(5);
+		assert_eq!(
+			(
+				diff.bytes_added,
+				diff.bytes_removed,
+				diff.items_added,
+				diff.items_removed,
+				diff.large_value_bytes_added,
+				diff.large_value_bytes_removed
+			),
+			(15, 3, 2, 1, 4, 2)
+		);
+
+		let literal = Diff {
+			bytes_added: 15,
+			bytes_removed: 3,
+			items_added: 2,
+			items_removed: 1,
+			large_value_bytes_added: 4,
+			large_value_bytes_removed: 2,
+		};
+		let info =
+			new_info(StorageInfo { bytes: 100, items: 10, bytes_deposit: 100, items_deposit: 20 });
+		assert_eq!(
+			diff.update_contract::<Test>(Some(&mut info.clone())),
+			literal.update_contract::<Test>(Some(&mut info.clone())),
+		);
+	}
+
+	#[test]
+	fn refund_releases_deposit_held_under_any_storage_reason() {
+		ExtBuilder::default().build().execute_with(|| {
+			let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000);
+			let _ = <Test as Config>::Currency::set_balance(&BOB, 100);
+			<Test as Config>::Currency::hold(&HoldReason::StorageDepositReserve.into(), &BOB, 10)
+				.unwrap();
+			let origin = Origin::from_account_id(ALICE);
+			let held = |reason: HoldReason| {
+				<Test as Config>::Currency::balance_on_hold(&reason.into(), &BOB)
+			};
+
+			// BOB can hold deposit under another storage deposit reason
+			let mut meter = Meter::<Test>::new(&origin, Some(100), 0).unwrap();
+			let mut nested = meter
+				.nested_with_reason(
+					BalanceOf::<Test>::zero(),
+					HoldReason::DelegateCallDepositReserve,
+				)
+				.unwrap();
+			nested.charge(&Diff { bytes_added: 5, ..Default::default() });
+			meter.absorb(nested, &BOB, None);
+			assert_eq!(meter.try_into_deposit(&origin).unwrap(), Deposit::Charge(5));
+			assert_eq!(held(HoldReason::StorageDepositReserve), 10);
+			assert_eq!(held(HoldReason::DelegateCallDepositReserve), 5);
+
+			// a refund exceeding what is held under its own reason releases the other reasons
+			let mut meter = Meter::<Test>::new(&origin, Some(100), 0).unwrap();
+			let mut nested = meter.nested(BalanceOf::<Test>::zero());
+			nested.charge_deposit(BOB, Deposit::Refund(12));
+			meter.absorb(nested, &BOB, None);
+			assert_eq!(meter.try_into_deposit(&origin).unwrap(), Deposit::Refund(12));
+			assert_eq!(held(HoldReason::StorageDepositReserve), 0);
+			assert_eq!(held(HoldReason::DelegateCallDepositReserve), 3);
+			assert_eq!(Pallet::<Test>::held_storage_deposit(&BOB), 3);
+		});
+	}
 }
@@ -36,7 +36,8 @@ use crate::{
 	weights::WeightInfo,
 	BalanceOf, Code, CodeHash, CodeInfoOf, CollectEvents, Config, ContractInfo, ContractInfoOf,
 	DebugInfo, DefaultAddressGenerator, DeletionQueueCounter, Error, HoldReason,
-	MigrationInProgress, Origin, Pallet, PristineCode, Schedule,
+	MigrationInProgress, Origin, Pallet, PristineCode, Schedule, StorageDeposit,
+	StorageDepositLimit, StorageRefundRebate,
 };
 use assert_matches::assert_matches;
 use codec::Encode;
@@ -47,7 +48,7 @@ use frame_support::{
 	storage::child,
 	traits::{
 		fungible::{BalancedHold, Inspect, Mutate, MutateHold},
-		tokens::Preservation,
+		tokens::{Precision, Preservation},
 		ConstU32, ConstU64, Contains, OnIdle, OnInitialize, StorageVersion,
 	},
 	weights::{constants::WEIGHT_REF_TIME_PER_SECOND, Weight},
@@ -61,7 +62,7 @@ use sp_keystore::{testing::MemoryKeystore, KeystoreExt};
 use sp_runtime::{
 	testing::H256,
 	traits::{BlakeTwo256, Convert, Hash, IdentityLookup},
-	AccountId32, BuildStorage, Perbill, TokenError,
+	AccountId32, BuildStorage, Perbill, Permill, TokenError,
 };
 
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -370,7 +371,7 @@ impl pallet_balances::Config for Test {
 	type FreezeIdentifier = ();
 	type MaxFreezes = ();
 	type RuntimeHoldReason = RuntimeHoldReason;
-	type MaxHolds = ConstU32<1>;
+	type MaxHolds = ConstU32<2>;
 }
 
 impl pallet_timestamp::Config for Test {
@@ -409,7 +410,10 @@ parameter_types! {
 		schedule
 	};
 	pub static DepositPerByte: BalanceOf<Test> = 1;
-	pub const DepositPerItem: BalanceOf<Test> = 2;
+	pub static DepositPerItem: BalanceOf<Test> = 2;
+	pub static LargeValueSurcharge: (u32, BalanceOf<Test>) = (0, 0);
+	pub static StorageRebate: (Weight, Weight) = Default::default();
+	pub static MaxDepositPerContract: Option<BalanceOf<Test>> = None;
 	pub static MaxDelegateDependencies: u32 = 32;
 
 	pub static CodeHashLockupDepositPercent: Perbill = Perbill::from_percent(0);
@@ -423,6 +427,18 @@ impl Convert<Weight, BalanceOf<Self>> for Test {
 	}
 }
 
+/// Rebates [`StorageRebate`] as `(per_byte, per_item)` for freed storage.
+pub struct TestStorageRefundRebate;
+
+impl StorageRefundRebate for TestStorageRefundRebate {
+	fn rebate(bytes: u32, items: u32) -> Weight {
+		let (per_byte, per_item) = StorageRebate::get();
+		per_byte
+			.saturating_mul(bytes.into())
+			.saturating_add(per_item.saturating_mul(items.into()))
+	}
+}
+
 /// A filter whose filter function can be swapped at runtime.
 pub struct TestFilter;
 
@@ -472,6 +488,9 @@ impl Config for Test {
 	type Schedule = MySchedule;
 	type DepositPerByte = DepositPerByte;
 	type DepositPerItem = DepositPerItem;
+	type LargeValueSurcharge = LargeValueSurcharge;
+	type StorageRefundRebate = TestStorageRefundRebate;
+	type MaxDepositPerContract = MaxDepositPerContract;
 	type DefaultDepositLimit = DefaultDepositLimit;
 	type AddressGenerator = DefaultAddressGenerator;
 	type MaxCodeLen = ConstU32<{ 123 * 1024 }>;
@@ -1434,6 +1453,212 @@ fn cannot_self_destruct_through_draning() {
 	});
 }
 
+#[test]
+fn deposit_headroom_works() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		// No ceiling is configured.
+		assert_eq!(Contracts::deposit_headroom(&addr), None);
+
+		MaxDepositPerContract::set(Some(500));
+		assert_eq!(Contracts::deposit_headroom(&addr), Some(500));
+		assert_eq!(Contracts::deposit_headroom(&BOB), None);
+
+		// Create 100 bytes of storage with a price of per byte and a single storage item of price 2
+		assert_ok!(Contracts::call(
+			RuntimeOrigin::signed(ALICE),
+			addr.clone(),
+			0,
+			GAS_LIMIT,
+			None,
+			100u32.to_le_bytes().to_vec()
+		));
+		assert_eq!(Contracts::deposit_headroom(&addr), Some(398));
+	});
+}
+
+#[test]
+fn storage_rebate_is_credited_for_freed_storage() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		let store = |size: u32| {
+			let result = Contracts::bare_call(
+				ALICE,
+				addr.clone(),
+				0,
+				GAS_LIMIT,
+				None,
+				size.to_le_bytes().to_vec(),
+				DebugInfo::Skip,
+				CollectEvents::Skip,
+				Determinism::Enforced,
+			);
+			assert_ok!(&result.result);
+			result
+		};
+
+		// Shrink a value by 100 bytes without any rebate.
+		store(100);
+		let without_rebate = store(0);
+
+		// The same with a rebate of 10 ref time per freed byte.
+		StorageRebate::set((Weight::from_parts(10, 0), Weight::zero()));
+		store(100);
+		let with_rebate = store(0);
+
+		assert!(matches!(with_rebate.storage_deposit, StorageDeposit::Refund(_)));
+		assert_eq!(
+			without_rebate.gas_consumed.saturating_sub(with_rebate.gas_consumed),
+			Weight::from_parts(1_000, 0)
+		);
+		// The rebate is credited after the fact and doesn't lower the gas required.
+		assert_eq!(with_rebate.gas_required, without_rebate.gas_required);
+	});
+}
+
+#[test]
+fn storage_rebate_is_not_credited_for_root() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		let store = |size: u32| {
+			Contracts::call(
+				RuntimeOrigin::root(),
+				addr.clone(),
+				0,
+				GAS_LIMIT,
+				None,
+				size.to_le_bytes().to_vec(),
+			)
+			.unwrap()
+			.actual_weight
+			.unwrap()
+		};
+
+		// Shrink a value by 100 bytes without any rebate.
+		store(100);
+		let without_rebate = store(0);
+
+		// Nothing is refunded to root. Hence the rebate isn't credited either.
+		StorageRebate::set((Weight::from_parts(10, 0), Weight::zero()));
+		store(100);
+		let with_rebate = store(0);
+
+		assert_eq!(with_rebate, without_rebate);
+	});
+}
+
+#[test]
+fn max_deposit_per_contract_is_enforced() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		// 100 bytes and a single item fit exactly into the ceiling
+		MaxDepositPerContract::set(Some(102));
+		assert_ok!(Contracts::call(
+			RuntimeOrigin::signed(ALICE),
+			addr.clone(),
+			0,
+			GAS_LIMIT,
+			None,
+			100u32.to_le_bytes().to_vec()
+		));
+		assert_eq!(get_contract(&addr).extra_deposit(), 102);
+
+		// a single byte more is rejected although the caller's limit would allow it
+		assert_err_ignore_postinfo!(
+			Contracts::call(
+				RuntimeOrigin::signed(ALICE),
+				addr.clone(),
+				0,
+				GAS_LIMIT,
+				None,
+				101u32.to_le_bytes().to_vec()
+			),
+			<Error<Test>>::ContractDepositLimitExhausted,
+		);
+		assert_eq!(get_contract(&addr).extra_deposit(), 102);
+
+		// a contract above a lowered ceiling can still free storage
+		MaxDepositPerContract::set(Some(10));
+		assert_ok!(Contracts::call(
+			RuntimeOrigin::signed(ALICE),
+			addr.clone(),
+			0,
+			GAS_LIMIT,
+			None,
+			50u32.to_le_bytes().to_vec()
+		));
+		assert_eq!(get_contract(&addr).extra_deposit(), 52);
+	});
+}
+
 #[test]
 fn cannot_self_destruct_through_storage_refund_after_price_change() {
 	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
@@ -3571,6 +3796,44 @@ fn upload_code_limit_too_low() {
 	});
 }
 
+#[test]
+fn instantiate_with_code_limit_exhausted_by_upload() {
+	let (wasm, _code_hash) = compile_module::<Test>("dummy").unwrap();
+	let deposit_expected = expected_deposit(wasm.len());
+
+	ExtBuilder::default().existential_deposit(100).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		// The upload consumes the whole limit which must not be mistaken for a zero limit.
+		assert_err_ignore_postinfo!(
+			Contracts::instantiate_with_code(
+				RuntimeOrigin::signed(ALICE),
+				0,
+				GAS_LIMIT,
+				Some(codec::Compact(deposit_expected)),
+				wasm.clone(),
+				vec![],
+				vec![],
+			),
+			<Error<Test>>::StorageDepositLimitExhausted,
+		);
+
+		let result = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			Some(deposit_expected),
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result;
+		assert_err!(result, <Error<Test>>::StorageDepositLimitExhausted);
+	});
+}
+
 #[test]
 fn upload_code_not_enough_balance() {
 	let (wasm, _code_hash) = compile_module::<Test>("dummy").unwrap();
@@ -4271,6 +4534,203 @@ fn slash_cannot_kill_account() {
 	});
 }
 
+#[test]
+fn refund_of_slashed_deposit_is_reconciled() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		// Create 100 bytes of storage and slash all but 40 of the held deposit.
+		assert_ok!(Contracts::call(
+			RuntimeOrigin::signed(ALICE),
+			addr.clone(),
+			0,
+			GAS_LIMIT,
+			None,
+			100u32.to_le_bytes().to_vec()
+		));
+		let held =
+			test_utils::get_balance_on_hold(&HoldReason::StorageDepositReserve.into(), &addr);
+		let _ = <Test as Config>::Currency::slash(
+			&HoldReason::StorageDepositReserve.into(),
+			&addr,
+			held - 40,
+		);
+
+		// Drop previous events
+		initialize_block(2);
+
+		// Freeing the storage can only refund what is left.
+		assert_ok!(Contracts::call(
+			RuntimeOrigin::signed(ALICE),
+			addr.clone(),
+			0,
+			GAS_LIMIT,
+			None,
+			0u32.to_le_bytes().to_vec()
+		));
+		assert!(System::events().contains(&EventRecord {
+			phase: Phase::Initialization,
+			event: RuntimeEvent::Contracts(crate::Event::StorageDepositReconciled {
+				contract: addr.clone(),
+				expected: 100,
+				refunded: 40,
+			}),
+			topics: vec![hash(&addr)],
+		}));
+	});
+}
+
+#[test]
+fn deposit_reconciliation_reports_drift_after_slash() {
+	let (wasm, _code_hash) = compile_module::<Test>("dummy").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		let info_deposit = test_utils::contract_info_storage_deposit(&addr);
+		assert_eq!(Contracts::deposit_reconciliation(&addr), Some((info_deposit, info_deposit, 0)));
+
+		// Slash half of the held deposit.
+		let _ = <Test as Config>::Currency::slash(
+			&HoldReason::StorageDepositReserve.into(),
+			&addr,
+			info_deposit / 2,
+		);
+
+		let held = info_deposit - info_deposit / 2;
+		assert_eq!(
+			Contracts::deposit_reconciliation(&addr),
+			Some((info_deposit, held, -((info_deposit / 2) as i128)))
+		);
+		assert_eq!(Contracts::deposit_reconciliation(&BOB), None);
+	});
+}
+
+#[test]
+fn deposit_reconciliation_counts_deposit_held_under_any_storage_reason() {
+	let (wasm, _code_hash) = compile_module::<Test>("dummy").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		// Move the deposit to the other reason a storage meter can hold it under.
+		let info_deposit = test_utils::contract_info_storage_deposit(&addr);
+		<Test as Config>::Currency::release(
+			&HoldReason::StorageDepositReserve.into(),
+			&addr,
+			info_deposit,
+			Precision::Exact,
+		)
+		.unwrap();
+		<Test as Config>::Currency::hold(
+			&HoldReason::DelegateCallDepositReserve.into(),
+			&addr,
+			info_deposit,
+		)
+		.unwrap();
+
+		// A code upload deposit held by the contract is not part of its storage deposit.
+		let _ = <Test as Config>::Currency::mint_into(&addr, 100);
+		<Test as Config>::Currency::hold(&HoldReason::CodeUploadDepositReserve.into(), &addr, 50)
+			.unwrap();
+
+		assert_eq!(Contracts::deposit_reconciliation(&addr), Some((info_deposit, info_deposit, 0)));
+		assert_ok!(get_contract(&addr).validate_deposit_consistency(&addr));
+	});
+}
+
+#[test]
+fn validate_deposit_consistency_works() {
+	let (wasm, _code_hash) = compile_module::<Test>("dummy").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		let mut info = get_contract(&addr);
+		assert_ok!(info.validate_deposit_consistency(&addr));
+
+		// the recorded deposit is more than what is actually held
+		info.storage_byte_deposit += 1;
+		assert_err!(
+			info.validate_deposit_consistency(&addr),
+			"Contract deposits exceed its held balance"
+		);
+
+		// deposits of delegate dependencies are held, too
+		info.storage_byte_deposit -= 1;
+		info.add_delegate_dependency(Default::default(), 1).unwrap();
+		assert_err!(
+			info.validate_deposit_consistency(&addr),
+			"Contract deposits exceed its held balance"
+		);
+
+		// the base deposit doesn't even cover the minimum balance
+		let info = ContractInfo::<Test>::new(&BOB, 0, Default::default()).unwrap();
+		assert_err!(
+			info.validate_deposit_consistency(&BOB),
+			"Contract base deposit doesn't cover the minimum balance"
+		);
+	});
+}
+
 #[test]
 fn contract_reverted() {
 	let (wasm, code_hash) = compile_module::<Test>("return_with_data").unwrap();
@@ -4775,16 +5235,17 @@ fn deposit_limit_in_nested_calls() {
 		);
 
 		// Refund in the callee contract but not enough to cover the 14 Balance required by the
-		// caller. Note that if previous sub-call wouldn't roll back, this call would pass making
-		// the test case fail. We don't set a special limit for the nested call here.
+		// caller: 12 Balance are refunded and the 2 Balance left exceed the limit of 1 Balance.
+		// Note that if previous sub-call wouldn't roll back, this call would pass making the test
+		// case fail. We don't set a special limit for the nested call here.
 		assert_err_ignore_postinfo!(
 			Contracts::call(
 				RuntimeOrigin::signed(ALICE),
 				addr_caller.clone(),
 				0,
 				GAS_LIMIT,
-				Some(codec::Compact(0)),
-				(87u32, &addr_callee, 0u64).encode(),
+				Some(codec::Compact(1)),
+				(88u32, &addr_callee, 0u64).encode(),
 			),
 			<Error<Test>>::StorageDepositLimitExhausted,
 		);
@@ -5118,6 +5579,161 @@ fn deposit_limit_honors_min_leftover() {
 	});
 }
 
+#[test]
+fn deposit_limit_can_be_a_percentage() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+		let _ = <Test as Config>::Currency::set_balance(&BOB, 1_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		// BOB can spend 600 on storage deposits as twice the existential deposit is kept back.
+		// Storing 100 bytes costs 102.
+		let call = |percent| {
+			Contracts::bare_call_with_limit(
+				BOB,
+				addr.clone(),
+				0,
+				GAS_LIMIT,
+				Some(StorageDepositLimit::Percent(Permill::from_percent(percent))),
+				100u32.to_le_bytes().to_vec(),
+				DebugInfo::Skip,
+				CollectEvents::Skip,
+				Determinism::Enforced,
+			)
+		};
+
+		// 10% are 60 which is not enough.
+		assert_err!(call(10).result, <Error<Test>>::StorageDepositLimitExhausted);
+		assert_eq!(<Test as Config>::Currency::free_balance(&BOB), 1_000);
+
+		// 20% are 120 which is enough.
+		let result = call(20);
+		assert_ok!(result.result);
+		assert_eq!(result.storage_deposit, StorageDeposit::Charge(102));
+		assert_eq!(<Test as Config>::Currency::free_balance(&BOB), 1_000 - 102);
+	});
+}
+
+#[test]
+fn sponsored_call_charges_and_refunds_the_payer() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+		let _ = <Test as Config>::Currency::set_balance(&BOB, 1_000);
+		let _ = <Test as Config>::Currency::set_balance(&CHARLIE, 1_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		let call = |len: u32| {
+			Contracts::bare_call_sponsored(
+				CHARLIE,
+				BOB,
+				addr.clone(),
+				0,
+				GAS_LIMIT,
+				None,
+				len.to_le_bytes().to_vec(),
+				DebugInfo::Skip,
+				CollectEvents::Skip,
+				Determinism::Enforced,
+			)
+		};
+
+		// Storing 100 bytes costs 102 which are paid by BOB.
+		let result = call(100);
+		assert_ok!(result.result);
+		assert_eq!(result.storage_deposit, StorageDeposit::Charge(102));
+		assert_eq!(<Test as Config>::Currency::free_balance(&BOB), 1_000 - 102);
+		assert_eq!(<Test as Config>::Currency::free_balance(&CHARLIE), 1_000);
+
+		// Freeing the bytes refunds BOB.
+		let result = call(0);
+		assert_ok!(result.result);
+		assert_eq!(result.storage_deposit, StorageDeposit::Refund(100));
+		assert_eq!(<Test as Config>::Currency::free_balance(&BOB), 1_000 - 2);
+		assert_eq!(<Test as Config>::Currency::free_balance(&CHARLIE), 1_000);
+	});
+}
+
+#[test]
+fn deposit_limit_reports_unavailable_min_leftover() {
+	let (wasm, _code_hash) = compile_module::<Test>("store_call").unwrap();
+	ExtBuilder::default().existential_deposit(200).build().execute_with(|| {
+		let _ = <Test as Config>::Currency::set_balance(&ALICE, 1_000_000);
+		let _ = <Test as Config>::Currency::set_balance(&BOB, 1_000);
+
+		let addr = Contracts::bare_instantiate(
+			ALICE,
+			0,
+			GAS_LIMIT,
+			None,
+			Code::Upload(wasm),
+			vec![],
+			vec![],
+			DebugInfo::Skip,
+			CollectEvents::Skip,
+		)
+		.result
+		.unwrap()
+		.account_id;
+
+		// the value send is more than BOB can spend
+		assert_err_ignore_postinfo!(
+			Contracts::call(
+				RuntimeOrigin::signed(BOB),
+				addr.clone(),
+				2_000,
+				GAS_LIMIT,
+				Some(codec::Compact(500)),
+				100u32.to_le_bytes().to_vec()
+			),
+			<Error<Test>>::StorageDepositLeftoverNotAvailable,
+		);
+		assert_eq!(<Test as Config>::Currency::free_balance(&BOB), 1_000);
+
+		// when the value can be kept it is the deposit that is reported as unaffordable
+		assert_err_ignore_postinfo!(
+			Contracts::call(
+				RuntimeOrigin::signed(BOB),
+				addr.clone(),
+				400,
+				GAS_LIMIT,
+				Some(codec::Compact(500)),
+				100u32.to_le_bytes().to_vec()
+			),
+			<Error<Test>>::StorageDepositNotEnoughFunds,
+		);
+	});
+}
+
 #[test]
 fn cannot_instantiate_indeterministic_code() {
 	let (wasm, code_hash) = compile_module::<Test>("float_instruction").unwrap();
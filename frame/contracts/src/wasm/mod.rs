@@ -463,7 +463,7 @@ mod tests {
 		assert_err, assert_ok, dispatch::DispatchResultWithPostInfo, weights::Weight,
 	};
 	use frame_system::pallet_prelude::BlockNumberFor;
-	use pallet_contracts_primitives::{ExecReturnValue, ReturnFlags};
+	use pallet_contracts_primitives::{ExecReturnValue, ReturnFlags, StorageDeposit};
 	use pretty_assertions::assert_eq;
 	use sp_core::H256;
 	use sp_runtime::DispatchError;
@@ -702,6 +702,16 @@ mod tests {
 			&mut self.gas_meter
 		}
 		fn charge_storage(&mut self, _diff: &crate::storage::meter::Diff) {}
+		fn charge_transient_storage(&mut self, _diff: &crate::storage::meter::Diff) {}
+		fn storage_deposit_available(&self) -> u64 {
+			u64::MAX
+		}
+		fn storage_deposit_limit(&self) -> u64 {
+			u64::MAX
+		}
+		fn storage_deposit_of_returned_calls(&self) -> StorageDeposit<u64> {
+			StorageDeposit::Charge(0)
+		}
 		fn append_debug_buffer(&mut self, msg: &str) -> bool {
 			self.debug_buffer.extend(msg.as_bytes());
 			true